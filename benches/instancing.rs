@@ -0,0 +1,58 @@
+// Draws the asteroid belt of `load_scene` as one instanced batch and as the same
+// copies added one by one to `Scene::objects`, each with its own pass through
+// the pipeline.
+//
+//     cargo bench --bench instancing
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nalgebra_glm::Vec3;
+use proyecto3::camera::Camera;
+use proyecto3::framebuffer::Framebuffer;
+use proyecto3::sphere::SphereKind;
+use proyecto3::{load_scene, render_scene, Scene, SceneObject};
+
+const SIZE: (usize, usize) = (320, 240);
+const ASTEROIDS: usize = 300;
+
+// The belt alone, without the planets, the sun or the sky
+fn belt(instanced: bool) -> Scene {
+    let mut scene = load_scene(SIZE.0, SIZE.1, SphereKind::Uv, ASTEROIDS);
+    scene.stars.clear();
+    scene.show_nave = false;
+    scene.objects.clear();
+    if !instanced {
+        let group = scene.instanced.remove(0);
+        scene.objects = group
+            .instances
+            .iter()
+            .map(|instance| SceneObject {
+                translation: instance.translation,
+                rotation: instance.rotation,
+                scale: instance.scale,
+                mesh: group.object.mesh.clone(),
+                ..group.object
+            })
+            .collect();
+    }
+    scene
+}
+
+fn instancing(c: &mut Criterion) {
+    let camera = Camera::new(Vec3::new(0.0, 8.0, 24.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let mut framebuffer = Framebuffer::new(SIZE.0, SIZE.1);
+
+    let mut group = c.benchmark_group("asteroid belt");
+    for (name, instanced) in [("separate objects", false), ("instanced", true)] {
+        let scene = belt(instanced);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                framebuffer.clear();
+                render_scene(&mut framebuffer, &scene, &camera, 0, 1)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, instancing);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+// Draws `assets/sphere.obj` as the triangle soup that `Obj::get_vertex_array`
+// returns and as the indexed mesh of `Obj::get_mesh`, which runs the vertex
+// shader once per distinct vertex instead of once per triangle corner.
+//
+//     cargo bench --bench vertex_cache
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nalgebra_glm::Vec3;
+use proyecto3::camera::Camera;
+use proyecto3::color::BlendMode;
+use proyecto3::debug_view::DebugView;
+use proyecto3::framebuffer::Framebuffer;
+use proyecto3::lod::Lod;
+use proyecto3::mesh::Mesh;
+use proyecto3::model::Obj;
+use proyecto3::triangle::{CullMode, FrontFace, RenderMode};
+use proyecto3::{render_scene, Scene, SceneObject};
+
+// Small enough for the geometry stages, not the fragment shader, to take most
+// of the time
+const SIZE: (usize, usize) = (32, 32);
+
+// The sphere filling most of the frame, shaded like the earth
+fn scene(mesh: Mesh) -> Scene {
+    let object = |mesh: Mesh| SceneObject {
+        translation: Vec3::zeros(),
+        rotation: Vec3::new(0.3, 0.6, 0.0),
+        scale: 2.0,
+        bounds: mesh.bounding_sphere(),
+        mesh: Lod::single(mesh),
+        id: 4.0,
+        cull_mode: CullMode::Back,
+        front_face: FrontFace::CounterClockwise,
+        early_depth_test: true,
+        blend_mode: BlendMode::Replace,
+        opacity: 1.0,
+    };
+    Scene {
+        objects: vec![object(mesh)],
+        instanced: Vec::new(),
+        nave: object(Mesh::default()),
+        show_nave: false,
+        stars: Vec::new(),
+        render_mode: RenderMode::Solid,
+        show_guides: false,
+        debug_view: DebugView::Shaded,
+        orbit_angles: vec![0.0],
+        orbit_speeds: vec![0.0],
+    }
+}
+
+fn vertex_cache(c: &mut Criterion) {
+    let obj = Obj::load("assets/sphere.obj").expect("Failed to load obj");
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let mut framebuffer = Framebuffer::new(SIZE.0, SIZE.1);
+
+    let mut group = c.benchmark_group("sphere.obj");
+    for (name, mesh) in [("triangle soup", Mesh::unindexed(obj.get_vertex_array())), ("indexed", obj.get_mesh())] {
+        let scene = scene(mesh);
+        framebuffer.clear();
+        let stats = render_scene(&mut framebuffer, &scene, &camera, 0, 1);
+        println!("{}: {} vertex shader runs", name, stats.vertices_shaded);
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                framebuffer.clear();
+                render_scene(&mut framebuffer, &scene, &camera, 0, 1)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, vertex_cache);
+criterion_main!(benches);
@@ -0,0 +1,188 @@
+use rayon::prelude::*;
+use crate::color::HdrColor;
+use crate::framebuffer::Framebuffer;
+
+// Mip levels are counted from this height, so a supersampled capture glows
+// like the window does instead of getting a proportionally smaller halo
+const BASE_HEIGHT: usize = 360;
+
+// Standard deviation of the Gaussian blurred into every level, in level pixels
+const SIGMA: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Bloom {
+    // Luminance above which emitted light starts to bleed
+    pub threshold: f32,
+    // Strength of the halo added back over the image
+    pub intensity: f32,
+    // Number of mip levels; each one doubles the reach of the halo
+    pub levels: usize,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom { threshold: 0.5, intensity: 0.4, levels: 5 }
+    }
+}
+
+// A float image at some mip level
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<HdrColor>,
+}
+
+impl Bloom {
+    // Spreads the bright part of `emission_buffer` over `hdr_buffer`. Runs
+    // between `Framebuffer::resolve` and `Framebuffer::present`.
+    pub fn apply(&self, framebuffer: &mut Framebuffer) {
+        let threshold = self.threshold;
+        let mut base = Level {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            pixels: framebuffer.emission_buffer.iter().map(|&color| bright_pass(color, threshold)).collect(),
+        };
+        while base.height > BASE_HEIGHT {
+            base = base.half();
+        }
+
+        // Every level is half the previous one, blurred
+        let kernel = gaussian_kernel(SIGMA);
+        let mut levels: Vec<Level> = Vec::with_capacity(self.levels);
+        for _ in 0..self.levels {
+            let source = levels.last().unwrap_or(&base);
+            if source.width < 2 || source.height < 2 {
+                break;
+            }
+            let level = source.half().blur(&kernel);
+            levels.push(level);
+        }
+        if levels.is_empty() {
+            return;
+        }
+
+        // Additive composite of every level, upsampled bilinearly
+        let width = framebuffer.width;
+        let height = framebuffer.height;
+        let weight = self.intensity / levels.len() as f32;
+        framebuffer.hdr_buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                let glow = levels.iter().fold(HdrColor::black(), |sum, level| sum + level.sample(u, v));
+                *pixel = *pixel + glow * weight;
+            }
+        });
+    }
+}
+
+// Keeps only the light above the threshold, preserving its hue
+fn bright_pass(color: HdrColor, threshold: f32) -> HdrColor {
+    let luminance = color.luminance();
+    if luminance <= threshold {
+        HdrColor::black()
+    } else {
+        color * ((luminance - threshold) / luminance)
+    }
+}
+
+// Normalized weights of a Gaussian cut at three standard deviations, center first
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as usize;
+    let weights: Vec<f32> = (0..=radius).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|w| w / total).collect()
+}
+
+impl Level {
+    fn at(&self, x: isize, y: isize) -> HdrColor {
+        // Pixels past the border repeat the edge
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // Box filters 2x2 blocks into one pixel
+    fn half(&self) -> Level {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let pixels = (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % width * 2) as isize, (i / width * 2) as isize);
+                (self.at(x, y) + self.at(x + 1, y) + self.at(x, y + 1) + self.at(x + 1, y + 1)) * 0.25
+            })
+            .collect();
+        Level { width, height, pixels }
+    }
+
+    // Separable Gaussian blur, horizontal then vertical
+    fn blur(&self, kernel: &[f32]) -> Level {
+        let pass = |source: &Level, dx: isize, dy: isize| -> Vec<HdrColor> {
+            (0..source.width * source.height)
+                .into_par_iter()
+                .map(|i| {
+                    let (x, y) = ((i % source.width) as isize, (i / source.width) as isize);
+                    let mut sum = source.at(x, y) * kernel[0];
+                    for (k, &w) in kernel.iter().enumerate().skip(1) {
+                        let k = k as isize;
+                        sum = sum + (source.at(x - k * dx, y - k * dy) + source.at(x + k * dx, y + k * dy)) * w;
+                    }
+                    sum
+                })
+                .collect()
+        };
+
+        let horizontal = Level { width: self.width, height: self.height, pixels: pass(self, 1, 0) };
+        let pixels = pass(&horizontal, 0, 1);
+        Level { width: self.width, height: self.height, pixels }
+    }
+
+    // Bilinear lookup at normalized coordinates
+    fn sample(&self, u: f32, v: f32) -> HdrColor {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        let top = self.at(x0, y0).lerp(&self.at(x0 + 1, y0), fx);
+        let bottom = self.at(x0, y0 + 1).lerp(&self.at(x0 + 1, y0 + 1), fx);
+        top.lerp(&bottom, fy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_is_normalized() {
+        let kernel = gaussian_kernel(SIGMA);
+        let total = kernel[0] + 2.0 * kernel[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn only_emissive_light_glows() {
+        let bright = HdrColor::new(4.0, 4.0, 4.0);
+        let mut framebuffer = Framebuffer::new(64, 48);
+        framebuffer.clear();
+        for y in 20..28 {
+            framebuffer.hdr_buffer[y * 64 + 28..y * 64 + 36].fill(bright);
+        }
+
+        // A bright surface that is merely lit does not bleed
+        let lit = framebuffer.hdr_buffer.clone();
+        Bloom::default().apply(&mut framebuffer);
+        assert_eq!(framebuffer.hdr_buffer, lit);
+
+        // The same surface marked as emissive spreads over its surroundings
+        for y in 20..28 {
+            framebuffer.emission_buffer[y * 64 + 28..y * 64 + 36].fill(bright);
+        }
+        Bloom::default().apply(&mut framebuffer);
+        assert!(framebuffer.hdr_buffer[24 * 64 + 24].r > 0.0);
+        assert!(framebuffer.hdr_buffer[24 * 64 + 32].r > bright.r);
+    }
+}
@@ -1,30 +1,32 @@
+use crate::color::HdrColor;
 use crate::framebuffer::Framebuffer;
-use crate::triangle::Triangle;
+use crate::line::Line;
+use crate::Uniforms;
 use nalgebra_glm::Vec3;
 
+// Curves in the orbital (XZ) plane, drawn as antialiased world-space lines
 pub trait Circle {
-    fn circle(&mut self, center: Vec3, radius: f32);
+    fn circle(&mut self, center: Vec3, radius: f32, color: HdrColor, uniforms: &Uniforms);
+
+    // `radii` are the semi-axes along x and z
+    fn ellipse(&mut self, center: Vec3, radii: (f32, f32), color: HdrColor, uniforms: &Uniforms);
 }
 
 impl Circle for Framebuffer {
-    fn circle(&mut self, center: Vec3, radius: f32) {
+    fn circle(&mut self, center: Vec3, radius: f32, color: HdrColor, uniforms: &Uniforms) {
+        self.ellipse(center, (radius, radius), color, uniforms);
+    }
+
+    fn ellipse(&mut self, center: Vec3, radii: (f32, f32), color: HdrColor, uniforms: &Uniforms) {
         let segments = 100; // Aumenta este número para suavizar los bordes
         let angle_step = 2.0 * std::f32::consts::PI / segments as f32;
+        let point_at = |theta: f32| center + Vec3::new(radii.0 * theta.cos(), 0.0, radii.1 * theta.sin());
 
         for i in 0..segments {
             let theta1 = i as f32 * angle_step;
             let theta2 = (i + 1) as f32 * angle_step;
 
-            let x1 = center.x + radius * theta1.cos();
-            let y1 = center.y + radius * theta1.sin();
-            let x2 = center.x + radius * theta2.cos();
-            let y2 = center.y + radius * theta2.sin();
-
-            let v1 = Vec3::new(x1, y1, center.z);
-            let v2 = Vec3::new(x2, y2, center.z);
-
-            // Rellenar el triángulo desde el centro al borde
-            self.triangle(center, v1, v2);
+            self.smooth_line(point_at(theta1), point_at(theta2), color, uniforms);
         }
     }
 }
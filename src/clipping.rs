@@ -0,0 +1,232 @@
+use nalgebra_glm::Vec4;
+use crate::vertex::Vertex;
+
+// Frustum planes in clip space (OpenGL convention, -w <= x, y, z <= w).
+// A point is inside a plane when dot(plane, clip_position) >= 0.
+const FRUSTUM_PLANES: [[f32; 4]; 6] = [
+    [0.0, 0.0, 1.0, 1.0],  // near:   z + w >= 0
+    [0.0, 0.0, -1.0, 1.0], // far:    w - z >= 0
+    [1.0, 0.0, 0.0, 1.0],  // left:   x + w >= 0
+    [-1.0, 0.0, 0.0, 1.0], // right:  w - x >= 0
+    [0.0, 1.0, 0.0, 1.0],  // bottom: y + w >= 0
+    [0.0, -1.0, 0.0, 1.0], // top:    w - y >= 0
+];
+
+fn plane_distance(plane: &[f32; 4], p: &Vec4) -> f32 {
+    plane[0] * p.x + plane[1] * p.y + plane[2] * p.z + plane[3] * p.w
+}
+
+// One bit per frustum plane the point lies outside of
+fn outcode(p: &Vec4) -> u8 {
+    FRUSTUM_PLANES.iter().enumerate().fold(0, |code, (i, plane)| {
+        if plane_distance(plane, p) < 0.0 { code | (1 << i) } else { code }
+    })
+}
+
+// Clips a triangle against the view frustum in homogeneous clip space.
+// Returns the resulting convex polygon (empty if the triangle is not visible),
+// with every attribute of the new vertices interpolated along the clipped edges.
+pub fn clip_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Vertex> {
+    clip_triangle_tracked(v1, v2, v3).into_iter().map(|clipped| clipped.vertex).collect()
+}
+
+// A vertex of a clipped polygon, with what it keeps of the original triangle
+#[derive(Clone, Debug)]
+pub struct ClippedVertex {
+    pub vertex: Vertex,
+    // Corner of the original triangle (0, 1 or 2) this vertex is, or None for a
+    // point where an edge crosses a frustum plane
+    pub corner: Option<usize>,
+    // Whether the polygon edge that starts here lies on an edge of the original
+    // triangle. Edges along a frustum plane are false.
+    pub original_edge: bool,
+}
+
+// Like `clip_triangle`, telling which vertices and edges of the polygon come
+// from the original triangle
+pub fn clip_triangle_tracked(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<ClippedVertex> {
+    let codes = [
+        outcode(&v1.clip_position),
+        outcode(&v2.clip_position),
+        outcode(&v3.clip_position),
+    ];
+
+    // Trivially rejected: all vertices are outside the same plane
+    if codes[0] & codes[1] & codes[2] != 0 {
+        return Vec::new();
+    }
+
+    let mut polygon: Vec<ClippedVertex> = [v1, v2, v3]
+        .into_iter()
+        .enumerate()
+        .map(|(corner, vertex)| ClippedVertex { vertex: vertex.clone(), corner: Some(corner), original_edge: true })
+        .collect();
+
+    // Trivially accepted: the whole triangle is inside the frustum
+    let crossed = codes[0] | codes[1] | codes[2];
+    if crossed == 0 {
+        return polygon;
+    }
+
+    for (i, plane) in FRUSTUM_PLANES.iter().enumerate() {
+        if crossed & (1 << i) == 0 {
+            continue;
+        }
+
+        polygon = clip_polygon(&polygon, plane);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    polygon
+}
+
+// Clips a segment against the view frustum in homogeneous clip space
+// (Liang-Barsky). Returns the visible part, or None if nothing is left.
+pub fn clip_line(v1: &Vertex, v2: &Vertex) -> Option<(Vertex, Vertex)> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+
+    for plane in FRUSTUM_PLANES.iter() {
+        let d1 = plane_distance(plane, &v1.clip_position);
+        let d2 = plane_distance(plane, &v2.clip_position);
+
+        if d1 < 0.0 && d2 < 0.0 {
+            return None;
+        }
+        if d1 < 0.0 {
+            t0 = t0.max(d1 / (d1 - d2));
+        } else if d2 < 0.0 {
+            t1 = t1.min(d1 / (d1 - d2));
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+    Some((v1.lerp(v2, t0), v1.lerp(v2, t1)))
+}
+
+// Sutherland-Hodgman step against a single plane. An edge cut by the plane
+// keeps its flag on the part that is left, and the edge that closes the
+// polygon along the plane is not part of the original triangle.
+fn clip_polygon(polygon: &[ClippedVertex], plane: &[f32; 4]) -> Vec<ClippedVertex> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let current = &polygon[i];
+        let next = &polygon[(i + 1) % polygon.len()];
+
+        let d_current = plane_distance(plane, &current.vertex.clip_position);
+        let d_next = plane_distance(plane, &next.vertex.clip_position);
+
+        if d_current >= 0.0 {
+            output.push(current.clone());
+        }
+
+        // The edge crosses the plane: emit the intersection point
+        if (d_current >= 0.0) != (d_next >= 0.0) {
+            let t = d_current / (d_current - d_next);
+            output.push(ClippedVertex {
+                vertex: current.vertex.lerp(&next.vertex, t),
+                corner: None,
+                // Leaving the frustum, the next edge runs along the plane
+                original_edge: d_current < 0.0 && current.original_edge,
+            });
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
+
+    fn clip_vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::zeros(), Vec3::zeros(), Vec2::zeros());
+        vertex.clip_position = Vec4::new(x, y, z, w);
+        vertex
+    }
+
+    fn textured_vertex(clip_position: Vec4, tex_coords: Vec2) -> Vertex {
+        let mut vertex = clip_vertex(clip_position.x, clip_position.y, clip_position.z, clip_position.w);
+        vertex.tex_coords = tex_coords;
+        vertex
+    }
+
+    fn assert_close(a: &Vertex, clip_position: Vec4, tex_coords: Vec2) {
+        assert!((a.clip_position - clip_position).magnitude() < 1e-5, "{:?}", a.clip_position);
+        assert!((a.tex_coords - tex_coords).magnitude() < 1e-5, "{:?}", a.tex_coords);
+    }
+
+    #[test]
+    fn triangle_inside_the_frustum_is_unchanged() {
+        let vertices = [
+            textured_vertex(Vec4::new(-0.5, -0.5, 0.0, 1.0), Vec2::new(0.0, 0.0)),
+            textured_vertex(Vec4::new(0.5, -0.5, 0.5, 1.0), Vec2::new(1.0, 0.0)),
+            textured_vertex(Vec4::new(0.0, 0.5, -0.5, 1.0), Vec2::new(0.0, 1.0)),
+        ];
+        let polygon = clip_triangle(&vertices[0], &vertices[1], &vertices[2]);
+        assert_eq!(polygon.len(), 3);
+        for (clipped, original) in polygon.iter().zip(&vertices) {
+            assert_eq!(clipped.clip_position, original.clip_position);
+            assert_eq!(clipped.tex_coords, original.tex_coords);
+        }
+    }
+
+    #[test]
+    fn triangle_crossing_the_near_plane_becomes_a_quad() {
+        // The first corner is behind the camera, the other two in front of it
+        let behind = textured_vertex(Vec4::new(0.0, 0.0, -3.0, 1.0), Vec2::new(0.0, 0.0));
+        let right = textured_vertex(Vec4::new(1.0, 0.0, 1.0, 3.0), Vec2::new(1.0, 0.0));
+        let up = textured_vertex(Vec4::new(0.0, 1.0, 1.0, 3.0), Vec2::new(0.0, 1.0));
+
+        let polygon = clip_triangle(&behind, &right, &up);
+        assert_eq!(polygon.len(), 4);
+        // The near plane cuts the edges from the hidden corner a third and two
+        // thirds of the way along, and the texture coordinates follow
+        assert_close(&polygon[0], Vec4::new(1.0 / 3.0, 0.0, -5.0 / 3.0, 5.0 / 3.0), Vec2::new(1.0 / 3.0, 0.0));
+        assert_close(&polygon[1], right.clip_position, right.tex_coords);
+        assert_close(&polygon[2], up.clip_position, up.tex_coords);
+        assert_close(&polygon[3], Vec4::new(0.0, 1.0 / 3.0, -5.0 / 3.0, 5.0 / 3.0), Vec2::new(0.0, 1.0 / 3.0));
+        assert!(polygon.iter().all(|vertex| vertex.clip_position.z + vertex.clip_position.w >= -1e-5));
+
+        // Only the edge closing the quad along the near plane is new, and the
+        // corners in front of the camera keep their place in the triangle
+        let tracked = clip_triangle_tracked(&behind, &right, &up);
+        let edges: Vec<bool> = tracked.iter().map(|clipped| clipped.original_edge).collect();
+        assert_eq!(edges, [true, true, true, false]);
+        let corners: Vec<Option<usize>> = tracked.iter().map(|clipped| clipped.corner).collect();
+        assert_eq!(corners, [None, Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn triangle_outside_the_frustum_is_dropped() {
+        // Every corner behind the camera
+        let behind = |x: f32| clip_vertex(x, 0.0, -2.0, 1.0);
+        assert!(clip_triangle(&behind(-0.5), &behind(0.5), &behind(0.0)).is_empty());
+
+        // No plane has all three corners outside it, but the triangle lies past
+        // the top-left corner of the frustum
+        let corner = [clip_vertex(-3.0, 0.5, 0.0, 1.0), clip_vertex(0.5, 3.0, 0.0, 1.0), clip_vertex(-3.0, 3.0, 0.0, 1.0)];
+        assert!(clip_triangle(&corner[0], &corner[1], &corner[2]).is_empty());
+    }
+
+    #[test]
+    fn line_crossing_the_near_plane_is_cut_there() {
+        // From behind the camera to the middle of the frustum
+        let (a, b) = clip_line(&clip_vertex(0.0, 0.0, -3.0, 1.0), &clip_vertex(0.0, 0.0, 1.0, 3.0)).unwrap();
+        assert!((a.clip_position.z + a.clip_position.w).abs() < 1e-6);
+        assert_eq!(b.clip_position, Vec4::new(0.0, 0.0, 1.0, 3.0));
+    }
+
+    #[test]
+    fn line_outside_the_frustum_is_dropped() {
+        // Both ends inside the left and right planes on their own, but the
+        // segment passes outside the top-left corner
+        assert!(clip_line(&clip_vertex(-2.0, 0.5, 0.0, 1.0), &clip_vertex(0.5, 3.0, 0.0, 1.0)).is_none());
+        assert!(clip_line(&clip_vertex(2.0, 0.0, 0.0, 1.0), &clip_vertex(3.0, 0.0, 0.0, 1.0)).is_none());
+    }
+}
@@ -5,17 +5,57 @@ pub struct Color {
   r: u8,
   g: u8,
   b: u8,
+  // opacity, 255 is fully opaque
+  a: u8,
+}
+
+// How a fragment is combined with the color already in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+  // Overwrite the destination and write depth (opaque geometry)
+  Replace,
+  Normal,
+  Multiply,
+  Add,
+  Subtract,
+  Screen,
+}
+
+// Per-channel formulas of the blend modes, shared by `Color` and `HdrColor`.
+// `d` and `s` are the destination and source channels and `one` the value of
+// full intensity. Results above `one` are left to the caller to clamp or keep.
+fn blend_channel(mode: BlendMode, d: f32, s: f32, one: f32) -> f32 {
+  match mode {
+    BlendMode::Replace | BlendMode::Normal => s,
+    BlendMode::Multiply => d * s / one,
+    BlendMode::Add => d + s,
+    BlendMode::Subtract => (d - s).max(0.0),
+    BlendMode::Screen => one - (one - d.min(one)) * (one - s.min(one)) / one,
+  }
 }
 
 impl Color {
   // Constructor to initialize the color using r, g, b values as u8
   pub fn new(r: u8, g: u8, b: u8) -> Self {
-    Color { r, g, b }
+    Color { r, g, b, a: 255 }
+  }
+
+  // Constructor with an explicit alpha channel
+  pub fn new_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+    Color { r, g, b, a }
   }
 
   // default color
   pub fn black() -> Self {
-    Color { r: 0, g: 0, b: 0 }
+    Color { r: 0, g: 0, b: 0, a: 255 }
+  }
+
+  pub fn alpha(&self) -> u8 {
+    self.a
+  }
+
+  pub fn with_alpha(self, a: u8) -> Self {
+    Color { a, ..self }
   }
 
   // New constructor to initialize the color using r, g, b values as f32 (0.0 to 1.0)
@@ -24,6 +64,7 @@ impl Color {
       r: (r.clamp(0.0, 1.0) * 255.0) as u8,
       g: (g.clamp(0.0, 1.0) * 255.0) as u8,
       b: (b.clamp(0.0, 1.0) * 255.0) as u8,
+      a: 255,
     }
   }
 
@@ -32,11 +73,11 @@ impl Color {
     let r = ((hex >> 16) & 0xFF) as u8;
     let g = ((hex >> 8) & 0xFF) as u8;
     let b = (hex & 0xFF) as u8;
-    Color { r, g, b }
+    Color { r, g, b, a: 255 }
   }
 
   // Function to return the color as a hex value
-  pub fn to_hex(&self) -> u32 {
+  pub fn to_hex(self) -> u32 {
     ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
   }
 
@@ -47,6 +88,7 @@ impl Color {
       r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
       g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
       b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+      a: (self.a as f32 + (other.a as f32 - self.a as f32) * t).round() as u8,
     }
   }
 
@@ -54,43 +96,114 @@ impl Color {
     self.r == 0 && self.g == 0 && self.b == 0 
   }
 
+  // `blend_channel` on each channel, clamped back to 8 bits
+  fn blend_channels(&self, blend: &Color, mode: BlendMode) -> Color {
+    let channel = |d: u8, s: u8| blend_channel(mode, d as f32, s as f32, 255.0).round().min(255.0) as u8;
+    Color::new(channel(self.r, blend.r), channel(self.g, blend.g), channel(self.b, blend.b))
+  }
+
   // New blend mode methods
   pub fn blend_normal(&self, blend: &Color) -> Color {
-    if blend.is_black() { *self } else { *blend }
+    self.blend_channels(blend, BlendMode::Normal)
   }
 
   pub fn blend_multiply(&self, blend: &Color) -> Color {
-    Color::new(
-      ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-      ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-      ((self.b as f32 * blend.b as f32) / 255.0) as u8
-    )
+    self.blend_channels(blend, BlendMode::Multiply)
   }
 
   pub fn blend_add(&self, blend: &Color) -> Color {
-    Color::new(
-      (self.r as u16 + blend.r as u16).min(255) as u8,
-      (self.g as u16 + blend.g as u16).min(255) as u8,
-      (self.b as u16 + blend.b as u16).min(255) as u8
-    )
+    self.blend_channels(blend, BlendMode::Add)
   }
 
   pub fn blend_subtract(&self, blend: &Color) -> Color {
-    let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-    let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-    let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
-
-    Color::new(r, g, b)
+    self.blend_channels(blend, BlendMode::Subtract)
   }
 
   pub fn blend_screen(&self, blend: &Color) -> Color {
-    Color::new(
-      255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-      255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-      255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8
-    )
+    self.blend_channels(blend, BlendMode::Screen)
+  }
+
+  // Combines `source` over this color with the given mode, then mixes the result
+  // in by the source's alpha. The result is always opaque.
+  pub fn blend(&self, source: &Color, mode: BlendMode) -> Color {
+    let blended = match mode {
+      BlendMode::Replace => return source.with_alpha(255),
+      BlendMode::Normal => self.blend_normal(source),
+      BlendMode::Multiply => self.blend_multiply(source),
+      BlendMode::Add => self.blend_add(source),
+      BlendMode::Subtract => self.blend_subtract(source),
+      BlendMode::Screen => self.blend_screen(source),
+    };
+
+    self.lerp(&blended, source.a as f32 / 255.0).with_alpha(255)
+  }
+
+}
+
+// Linear color with float channels, free to go above 1.0 for bright light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrColor {
+  pub r: f32,
+  pub g: f32,
+  pub b: f32,
+  pub a: f32,
+}
+
+impl HdrColor {
+  pub fn new(r: f32, g: f32, b: f32) -> Self {
+    HdrColor { r, g, b, a: 1.0 }
+  }
+
+  pub fn black() -> Self {
+    HdrColor::new(0.0, 0.0, 0.0)
+  }
+
+  pub fn with_alpha(self, a: f32) -> Self {
+    HdrColor { a, ..self }
   }
 
+  // Relative luminance (Rec. 709 weights)
+  pub fn luminance(&self) -> f32 {
+    0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+  }
+
+  pub fn lerp(&self, other: &HdrColor, t: f32) -> Self {
+    let t = t.clamp(0.0, 1.0);
+    HdrColor {
+      r: self.r + (other.r - self.r) * t,
+      g: self.g + (other.g - self.g) * t,
+      b: self.b + (other.b - self.b) * t,
+      a: self.a + (other.a - self.a) * t,
+    }
+  }
+
+  // Float counterpart of `Color::blend`, without clamping the result
+  pub fn blend(&self, source: &HdrColor, mode: BlendMode) -> HdrColor {
+    let channel = |d: f32, s: f32| blend_channel(mode, d, s, 1.0);
+    let blended = HdrColor::new(
+      channel(self.r, source.r),
+      channel(self.g, source.g),
+      channel(self.b, source.b),
+    );
+
+    match mode {
+      BlendMode::Replace => blended,
+      _ => self.lerp(&blended, source.a).with_alpha(1.0),
+    }
+  }
+}
+
+// 8-bit colors are sRGB encoded: decode them to linear light
+impl From<Color> for HdrColor {
+  fn from(color: Color) -> Self {
+    let decode = |c: u8| (c as f32 / 255.0).powf(2.2);
+    HdrColor {
+      r: decode(color.r),
+      g: decode(color.g),
+      b: decode(color.b),
+      a: color.a as f32 / 255.0,
+    }
+  }
 }
 
 // Implement addition for Color
@@ -104,6 +217,7 @@ impl Add for Color {
       r: self.r.saturating_add(other.r),
       g: self.g.saturating_add(other.g),
       b: self.b.saturating_add(other.b),
+      a: self.a.max(other.a),
     }
   }
 }
@@ -119,6 +233,33 @@ impl Mul<f32> for Color {
       r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
       g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
       b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+      a: self.a,
+    }
+  }
+}
+
+impl Add for HdrColor {
+  type Output = HdrColor;
+
+  fn add(self, other: HdrColor) -> HdrColor {
+    HdrColor {
+      r: self.r + other.r,
+      g: self.g + other.g,
+      b: self.b + other.b,
+      a: self.a.max(other.a),
+    }
+  }
+}
+
+impl Mul<f32> for HdrColor {
+  type Output = HdrColor;
+
+  fn mul(self, scalar: f32) -> HdrColor {
+    HdrColor {
+      r: self.r * scalar,
+      g: self.g * scalar,
+      b: self.b * scalar,
+      a: self.a,
     }
   }
 }
@@ -126,6 +267,22 @@ impl Mul<f32> for Color {
 // Implement display formatting for Color
 impl fmt::Display for Color {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+    write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn alpha_mixes_the_blend_result_over_the_destination() {
+    let destination = Color::new(0, 0, 200);
+    let source = Color::new_rgba(200, 100, 0, 128);
+
+    assert_eq!(destination.blend(&source, BlendMode::Normal).to_hex(), 0x643264);
+    assert_eq!(destination.blend(&source, BlendMode::Add).to_hex(), 0x6432C8);
+    assert_eq!(destination.blend(&source, BlendMode::Replace).to_hex(), 0xC86400);
+    assert_eq!(destination.blend(&source.with_alpha(0), BlendMode::Normal).to_hex(), 0x0000C8);
   }
 }
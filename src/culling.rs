@@ -0,0 +1,92 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// A sphere that contains every vertex of a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    // Centered on the axis-aligned box of the points, reaching the farthest one.
+    // Not the smallest sphere, but close for the round models of the scene.
+    pub fn from_points<'a>(points: impl Iterator<Item = &'a Vec3> + Clone) -> Self {
+        let (min, max) = points.clone().fold(
+            (Vec3::repeat(f32::INFINITY), Vec3::repeat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.inf(p), max.sup(p)),
+        );
+        if min.x > max.x {
+            return BoundingSphere { center: Vec3::zeros(), radius: 0.0 };
+        }
+
+        let center = (min + max) * 0.5;
+        let radius = points.map(|p| (p - center).magnitude()).fold(0.0, f32::max);
+        BoundingSphere { center, radius }
+    }
+
+    // The sphere after `matrix`, grown by the largest scale of its axes so it
+    // still contains the model under non-uniform scaling
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        let center = matrix * Vec4::new(self.center.x, self.center.y, self.center.z, 1.0);
+        let scale = (0..3)
+            .map(|axis| matrix.fixed_view::<3, 1>(0, axis).magnitude())
+            .fold(0.0, f32::max);
+        BoundingSphere { center: center.xyz(), radius: self.radius * scale }
+    }
+}
+
+// The six planes that bound what a camera sees, as (normal, distance) pairs
+// with the normals pointing inwards
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // Extracts the planes of a view-projection matrix with an OpenGL-style clip
+    // space, where -w <= x, y, z <= w (Gribb and Hartmann)
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| plane / plane.xyz().magnitude());
+        Frustum { planes }
+    }
+
+    // False only when the sphere lies entirely outside one of the planes
+    pub fn intersects(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| plane.xyz().dot(&sphere.center) + plane.w >= -sphere.radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{look_at, perspective};
+
+    #[test]
+    fn sphere_contains_every_point() {
+        let points = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 0.0)];
+        let sphere = BoundingSphere::from_points(points.iter());
+        assert_eq!(sphere.center, Vec3::new(1.0, 1.0, 0.0));
+        assert!(points.iter().all(|p| (p - sphere.center).magnitude() <= sphere.radius + 1e-6));
+
+        let moved = sphere.transform(&Mat4::new_translation(&Vec3::new(0.0, 0.0, 5.0)).prepend_scaling(2.0));
+        assert_eq!(moved.center, Vec3::new(2.0, 2.0, 5.0));
+        assert!((moved.radius - sphere.radius * 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn spheres_outside_the_frustum_are_rejected() {
+        let view = look_at(&Vec3::new(0.0, 0.0, 20.0), &Vec3::zeros(), &Vec3::new(0.0, 1.0, 0.0));
+        let frustum = Frustum::from_matrix(&(perspective(4.0 / 3.0, 0.8, 0.1, 1000.0) * view));
+        let sphere = |x: f32, y: f32, z: f32, radius: f32| BoundingSphere { center: Vec3::new(x, y, z), radius };
+
+        assert!(frustum.intersects(&sphere(0.0, 0.0, 0.0, 1.0)));
+        // Behind the camera
+        assert!(!frustum.intersects(&sphere(0.0, 0.0, 25.0, 1.0)));
+        // Off to the side, and the same sphere grown until it pokes into view
+        assert!(!frustum.intersects(&sphere(40.0, 0.0, 0.0, 1.0)));
+        assert!(frustum.intersects(&sphere(40.0, 0.0, 0.0, 30.0)));
+        // Past the far plane
+        assert!(!frustum.intersects(&sphere(0.0, 0.0, -1200.0, 10.0)));
+    }
+}
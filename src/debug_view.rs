@@ -0,0 +1,134 @@
+use rayon::prelude::*;
+use crate::color::HdrColor;
+use crate::fragment::Fragment;
+use crate::framebuffer::Framebuffer;
+use crate::{FAR, NEAR};
+
+// What ends up on screen: the shaded image, or one of the inputs of the
+// shaders shown as colors, to tell geometry problems from shading ones
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    Shaded,
+    // Linear distance to the camera from the resolved depth buffer, false-colored
+    // from red (nearest) to blue (farthest) over the range of the frame
+    Depth,
+    // Interpolated world-space normal, each axis mapped from [-1, 1] to [0, 1]
+    Normal,
+    // Texture coordinates as red and green
+    TexCoords,
+    // Object-space position, each axis mapped from [-1, 1] to [0, 1]
+    Position,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Shaded => DebugView::Depth,
+            DebugView::Depth => DebugView::Normal,
+            DebugView::Normal => DebugView::TexCoords,
+            DebugView::TexCoords => DebugView::Position,
+            DebugView::Position => DebugView::Shaded,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Shaded => "shaded",
+            DebugView::Depth => "depth",
+            DebugView::Normal => "normal",
+            DebugView::TexCoords => "uv",
+            DebugView::Position => "position",
+        }
+    }
+
+    // The color written instead of running the fragment shader, or None in the
+    // shaded view. The depth view writes black and fills in the image from the
+    // depth buffer once the frame is resolved.
+    pub fn fragment_color(self, fragment: &Fragment) -> Option<HdrColor> {
+        let signed = |v: f32| (v * 0.5 + 0.5).clamp(0.0, 1.0);
+        match self {
+            DebugView::Shaded => None,
+            DebugView::Depth => Some(HdrColor::black()),
+            DebugView::Normal => {
+                let n = fragment.normal;
+                Some(HdrColor::new(signed(n.x), signed(n.y), signed(n.z)))
+            }
+            DebugView::TexCoords => {
+                let uv = fragment.tex_coords;
+                Some(HdrColor::new(uv.x.clamp(0.0, 1.0), uv.y.clamp(0.0, 1.0), 0.0))
+            }
+            DebugView::Position => {
+                let p = fragment.vertex_position;
+                Some(HdrColor::new(signed(p.x), signed(p.y), signed(p.z)))
+            }
+        }
+    }
+
+    // Replaces `Framebuffer::tone_map` for the debug views: the resolved colors
+    // go to the screen untouched, or the false-colored depth in the depth view
+    pub fn display(self, framebuffer: &mut Framebuffer) {
+        if self != DebugView::Depth {
+            framebuffer.display_buffer.copy_from_slice(&framebuffer.hdr_buffer);
+            return;
+        }
+
+        let distances: Vec<f32> = framebuffer.depth_buffer.par_iter().map(|&depth| linearize_depth(depth)).collect();
+        let (nearest, farthest) = distances
+            .iter()
+            .filter(|distance| distance.is_finite())
+            .fold((f32::INFINITY, 0.0_f32), |(lo, hi), &distance| (lo.min(distance), hi.max(distance)));
+        let range = (farthest - nearest).max(1e-6);
+
+        framebuffer.display_buffer.par_iter_mut().zip(distances.par_iter()).for_each(|(pixel, &distance)| {
+            *pixel = if distance.is_finite() { false_color((distance - nearest) / range) } else { HdrColor::black() };
+        });
+    }
+}
+
+// Distance along the view axis for a depth in normalized device coordinates,
+// infinite where nothing was drawn
+pub fn linearize_depth(depth: f32) -> f32 {
+    if !depth.is_finite() {
+        return f32::INFINITY;
+    }
+    2.0 * FAR * NEAR / (FAR + NEAR - depth * (FAR - NEAR))
+}
+
+// Red, yellow, green, cyan and blue, evenly spaced over [0, 1]
+fn false_color(t: f32) -> HdrColor {
+    const STOPS: [(f32, f32, f32); 5] = [
+        (1.0, 0.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (0.0, 1.0, 0.0),
+        (0.0, 1.0, 1.0),
+        (0.0, 0.0, 1.0),
+    ];
+    let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position.floor() as usize).min(STOPS.len() - 2);
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    HdrColor::new(a.0, a.1, a.2).lerp(&HdrColor::new(b.0, b.1, b.2), position - index as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{perspective, Vec4};
+
+    #[test]
+    fn depth_is_linearized_back_to_distance() {
+        let projection = perspective(1.0, 0.8, NEAR, FAR);
+        for distance in [0.5, 3.0, 24.0, 400.0] {
+            let clip = projection * Vec4::new(0.0, 0.0, -distance, 1.0);
+            let linear = linearize_depth(clip.z / clip.w);
+            assert!((linear - distance).abs() / distance < 1e-3, "{} -> {}", distance, linear);
+        }
+        assert_eq!(linearize_depth(f32::INFINITY), f32::INFINITY);
+    }
+
+    #[test]
+    fn false_color_runs_from_red_to_blue() {
+        assert_eq!(false_color(0.0), HdrColor::new(1.0, 0.0, 0.0));
+        assert_eq!(false_color(0.5), HdrColor::new(0.0, 1.0, 0.0));
+        assert_eq!(false_color(1.0), HdrColor::new(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs;
+use std::io;
+use rayon::prelude::*;
+use crate::color::HdrColor;
+use crate::post::{PostEffect, PostFrame};
+
+// Darkens the corners of the image
+pub struct Vignette {
+    // How dark the corners get, 0 to 1
+    pub strength: f32,
+    // Distance from the center where darkening starts, relative to the half diagonal
+    pub radius: f32,
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &str {
+        "vignette"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let (cx, cy) = (frame.width as f32 * 0.5, frame.height as f32 * 0.5);
+        let half_diagonal = (cx * cx + cy * cy).sqrt();
+        frame.color.par_chunks_mut(frame.width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                let distance = (dx * dx + dy * dy).sqrt() / half_diagonal;
+                *pixel = *pixel * (1.0 - self.strength * smoothstep(self.radius, 1.0, distance));
+            }
+        });
+    }
+}
+
+// Animated monochrome noise, strongest in the midtones
+pub struct FilmGrain {
+    pub amount: f32,
+}
+
+impl PostEffect for FilmGrain {
+    fn name(&self) -> &str {
+        "grain"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let time = frame.time;
+        frame.color.par_chunks_mut(frame.width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let noise = hash(x as u32, y as u32, time) * 2.0 - 1.0;
+                let luminance = pixel.luminance().clamp(0.0, 1.0);
+                let grain = noise * self.amount * (0.25 + 3.0 * luminance * (1.0 - luminance));
+                *pixel = *pixel + HdrColor::new(grain, grain, grain);
+            }
+        });
+    }
+}
+
+// Splits red and blue apart towards the edges, like a cheap lens
+pub struct ChromaticAberration {
+    // Offset of the red and blue channels at the border, relative to the distance from the center
+    pub strength: f32,
+}
+
+impl PostEffect for ChromaticAberration {
+    fn name(&self) -> &str {
+        "chromatic aberration"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let source = frame.color.to_vec();
+        let (width, height) = (frame.width, frame.height);
+        let sample = |x: f32, y: f32| bilinear(&source, width, height, x, y);
+        let (cx, cy) = (width as f32 * 0.5, height as f32 * 0.5);
+
+        frame.color.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let (ox, oy) = ((px - cx) * self.strength, (py - cy) * self.strength);
+                pixel.r = sample(px + ox, py + oy).r;
+                pixel.b = sample(px - ox, py - oy).b;
+            }
+        });
+    }
+}
+
+// Remaps colors through a 3D lookup table
+pub struct ColorGrading {
+    pub lut: Lut3d,
+}
+
+impl PostEffect for ColorGrading {
+    fn name(&self) -> &str {
+        "color grading"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        frame.color.par_iter_mut().for_each(|pixel| *pixel = self.lut.sample(*pixel).with_alpha(pixel.a));
+    }
+}
+
+// A `size`^3 grid of output colors over the [0, 1] RGB cube, red varying fastest
+// (the layout of .cube files)
+pub struct Lut3d {
+    size: usize,
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    // Fills the table by evaluating `grade` at every grid point
+    pub fn from_fn(size: usize, grade: impl Fn(f32, f32, f32) -> [f32; 3]) -> Self {
+        assert!(size >= 2, "a LUT needs at least two entries per axis");
+        let step = 1.0 / (size - 1) as f32;
+        let table = (0..size * size * size)
+            .map(|i| grade((i % size) as f32 * step, (i / size % size) as f32 * step, (i / (size * size)) as f32 * step))
+            .collect();
+        Lut3d { size, table }
+    }
+
+    // A warm grade with a gentle S-curve, used when no .cube file is given
+    pub fn warm() -> Self {
+        Lut3d::from_fn(17, |r, g, b| {
+            let contrast = |c: f32| c + 0.15 * (c - 0.5) * (1.0 - (2.0 * c - 1.0).abs());
+            [contrast(r * 1.04).min(1.0), contrast(g), contrast(b * 0.92)]
+        })
+    }
+
+    // Reads an Adobe .cube file with a LUT_3D_SIZE table in the [0, 1] domain
+    pub fn load_cube(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Lut3d::parse_cube(&text).map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+
+    fn parse_cube(text: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut table = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("LUT_3D_SIZE") => {
+                    let value = fields.next().and_then(|v| v.parse::<usize>().ok());
+                    size = Some(value.filter(|&n| n >= 2).ok_or_else(|| format!("invalid size: {}", line))?);
+                }
+                Some(keyword) if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    // TITLE, DOMAIN_MIN, DOMAIN_MAX, ... are not used
+                }
+                Some(first) => {
+                    let values: Vec<f32> = std::iter::once(first)
+                        .chain(fields)
+                        .map(|v| v.parse::<f32>().map_err(|_| format!("invalid entry: {}", line)))
+                        .collect::<Result<_, _>>()?;
+                    match values[..] {
+                        [r, g, b] => table.push([r, g, b]),
+                        _ => return Err(format!("expected three values: {}", line)),
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if table.len() != size * size * size {
+            return Err(format!("expected {} entries, found {}", size * size * size, table.len()));
+        }
+        Ok(Lut3d { size, table })
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> HdrColor {
+        let [r, g, b] = self.table[(b * self.size + g) * self.size + r];
+        HdrColor::new(r, g, b)
+    }
+
+    // Trilinear lookup; input channels are clamped to [0, 1]
+    pub fn sample(&self, color: HdrColor) -> HdrColor {
+        let last = (self.size - 1) as f32;
+        let split = |c: f32| {
+            let position = c.clamp(0.0, 1.0) * last;
+            let index = (position.floor() as usize).min(self.size - 2);
+            (index, position - index as f32)
+        };
+        let ((r, fr), (g, fg), (b, fb)) = (split(color.r), split(color.g), split(color.b));
+
+        let plane = |b: usize| {
+            let bottom = self.at(r, g, b).lerp(&self.at(r + 1, g, b), fr);
+            let top = self.at(r, g + 1, b).lerp(&self.at(r + 1, g + 1, b), fr);
+            bottom.lerp(&top, fg)
+        };
+        plane(b).lerp(&plane(b + 1), fb)
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Uniform value in [0, 1] for every pixel and frame
+fn hash(x: u32, y: u32, time: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841) ^ time.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    h as f32 / u32::MAX as f32
+}
+
+// Bilinear lookup at pixel coordinates, repeating the edge past the border
+fn bilinear(pixels: &[HdrColor], width: usize, height: usize, x: f32, y: f32) -> HdrColor {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let at = |x: f32, y: f32| {
+        let x = (x as isize).clamp(0, width as isize - 1) as usize;
+        let y = (y as isize).clamp(0, height as isize - 1) as usize;
+        pixels[y * width + x]
+    };
+
+    let top = at(x0, y0).lerp(&at(x0 + 1.0, y0), fx);
+    let bottom = at(x0, y0 + 1.0).lerp(&at(x0 + 1.0, y0 + 1.0), fx);
+    top.lerp(&bottom, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_cube_leaves_colors_unchanged() {
+        let mut text = String::from("TITLE \"identity\"\nLUT_3D_SIZE 2\n");
+        for i in 0..8 {
+            text += &format!("{} {} {}\n", i & 1, (i >> 1) & 1, (i >> 2) & 1);
+        }
+        let lut = Lut3d::parse_cube(&text).unwrap();
+
+        let color = HdrColor::new(0.2, 0.55, 0.9);
+        let graded = lut.sample(color);
+        for (a, b) in [(graded.r, color.r), (graded.g, color.g), (graded.b, color.b)] {
+            assert!((a - b).abs() < 1e-5, "{:?} -> {:?}", color, graded);
+        }
+    }
+
+    #[test]
+    fn cube_with_missing_entries_is_rejected() {
+        assert!(Lut3d::parse_cube("LUT_3D_SIZE 2\n0 0 0\n1 0 0\n").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::BufWriter;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageResult, RgbImage};
+
+// Converts a 0xRRGGBB buffer, as stored in `Framebuffer::buffer`, into an image
+pub fn to_rgb_image(pixels: &[u32], width: usize, height: usize) -> RgbImage {
+    let mut image = RgbImage::new(width as u32, height as u32);
+    for (pixel, &color) in image.pixels_mut().zip(pixels) {
+        pixel.0 = [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+    }
+    image
+}
+
+// Saves the buffer to disk; the format (PNG, JPEG, ...) follows the file extension
+pub fn save_image(path: &str, pixels: &[u32], width: usize, height: usize) -> ImageResult<()> {
+    to_rgb_image(pixels, width, height).save(path)
+}
+
+// Destination of a recording: an animated GIF when the path ends in .gif, or
+// else one numbered image per frame next to the path (frame.png becomes
+// frame_0000.png, frame_0001.png, ...)
+pub enum FrameWriter {
+    Sequence { path: String, digits: usize, next: usize },
+    Gif { encoder: GifEncoder<BufWriter<File>>, delay: Delay },
+}
+
+impl FrameWriter {
+    pub fn create(path: &str, frames: usize, fps: u32) -> ImageResult<Self> {
+        if !path.to_ascii_lowercase().ends_with(".gif") {
+            let digits = frames.saturating_sub(1).to_string().len().max(4);
+            return Ok(FrameWriter::Sequence { path: path.to_string(), digits, next: 0 });
+        }
+
+        // Every frame gets its own palette; speed 10 is the quantizer's
+        // recommended balance between quality and time
+        let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(FrameWriter::Gif { encoder, delay: Delay::from_numer_denom_ms(1000, fps.max(1)) })
+    }
+
+    // Appends a 0xRRGGBB buffer as the next frame
+    pub fn write(&mut self, pixels: &[u32], width: usize, height: usize) -> ImageResult<()> {
+        match self {
+            FrameWriter::Sequence { path, digits, next } => {
+                let frame_path = numbered_path(path, *next, *digits);
+                *next += 1;
+                save_image(&frame_path, pixels, width, height)
+            }
+            FrameWriter::Gif { encoder, delay } => {
+                let image = DynamicImage::ImageRgb8(to_rgb_image(pixels, width, height)).into_rgba8();
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, *delay))
+            }
+        }
+    }
+}
+
+// "out/frame.png", 7, 4 -> "out/frame_0007.png"
+fn numbered_path(path: &str, index: usize, digits: usize) -> String {
+    let file_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    match path[file_start..].rfind('.') {
+        Some(dot) => {
+            let (stem, extension) = path.split_at(file_start + dot);
+            format!("{}_{:0width$}{}", stem, index, extension, width = digits)
+        }
+        None => format!("{}_{:0width$}", path, index, width = digits),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_frames_are_numbered_before_the_extension() {
+        assert_eq!(numbered_path("out/frame.png", 7, 4), "out/frame_0007.png");
+        assert_eq!(numbered_path("run.v2/frame", 12, 5), "run.v2/frame_00012");
+        assert_eq!(numbered_path("frame.jpg", 12345, 4), "frame_12345.jpg");
+    }
+}
@@ -1,5 +1,6 @@
 use nalgebra_glm::{Vec3, Vec2};
-use crate::color::Color;
+use crate::color::{Color, HdrColor};
+use crate::framebuffer::MAX_SAMPLES;
 
 pub struct Fragment {
     pub position: Vec2,
@@ -9,6 +10,9 @@ pub struct Fragment {
     pub intensity: f32,
     pub vertex_position: Vec3,
     pub tex_coords: Vec2,
+    // Bit mask of the MSAA samples covered by the triangle, and their depths
+    pub coverage: u8,
+    pub sample_depths: [f32; MAX_SAMPLES],
 }
 
 impl Fragment {
@@ -29,8 +33,36 @@ impl Fragment {
             intensity,
             vertex_position,
             tex_coords,
+            coverage: 1,
+            sample_depths: [depth; MAX_SAMPLES],
         }
     }
 }
 
+// What the fragment shader writes: the lit surface color and the light the
+// surface gives off on its own, which feeds the bloom pass
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentOutput {
+    pub color: HdrColor,
+    pub emission: HdrColor,
+}
 
+impl FragmentOutput {
+    // A surface that glows with its own color, `intensity` times as bright as
+    // the surface itself
+    pub fn emissive(color: HdrColor, intensity: f32) -> Self {
+        FragmentOutput { color, emission: color * intensity }
+    }
+}
+
+impl From<HdrColor> for FragmentOutput {
+    fn from(color: HdrColor) -> Self {
+        FragmentOutput { color, emission: HdrColor::black() }
+    }
+}
+
+impl From<Color> for FragmentOutput {
+    fn from(color: Color) -> Self {
+        HdrColor::from(color).into()
+    }
+}
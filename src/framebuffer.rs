@@ -1,62 +1,280 @@
 // framebuffer.rs
-use rand::Rng; 
+use rayon::prelude::*;
+use crate::color::{BlendMode, Color, HdrColor};
+use crate::fragment::FragmentOutput;
+use crate::tonemap::{quantize, ToneMapping};
 
+// Height in pixels of each screen tile handed to a worker thread
+pub const TILE_HEIGHT: usize = 16;
+
+// Largest supported MSAA mode
+pub const MAX_SAMPLES: usize = 8;
+
+// Standard sample positions inside a pixel for each MSAA mode (same layout as D3D)
+const SAMPLES_1X: [(f32, f32); 1] = [(0.5, 0.5)];
+const SAMPLES_2X: [(f32, f32); 2] = [(0.75, 0.75), (0.25, 0.25)];
+const SAMPLES_4X: [(f32, f32); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+const SAMPLES_8X: [(f32, f32); 8] = [
+    (0.5625, 0.3125), (0.4375, 0.6875), (0.8125, 0.5625), (0.3125, 0.1875),
+    (0.1875, 0.8125), (0.0625, 0.4375), (0.6875, 0.9375), (0.9375, 0.0625),
+];
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    // Tone mapped image, one color per pixel, ready to be shown
     pub buffer: Vec<u32>,
+    // Resolved linear light, one color per pixel
+    pub hdr_buffer: Vec<HdrColor>,
+    // Resolved light emitted by glowing surfaces, the input of the bloom pass
+    pub emission_buffer: Vec<HdrColor>,
+    // Tone mapped colors in [0, 1], where post effects run before `present`
+    pub display_buffer: Vec<HdrColor>,
+    // Nearest depth among the samples of each pixel
+    pub depth_buffer: Vec<f32>,
+    // Color, emission and depth per sample, `samples` consecutive entries per pixel
+    pub color_samples: Vec<HdrColor>,
+    pub emission_samples: Vec<HdrColor>,
     pub zbuffer: Vec<f32>,
+    samples: usize,
     background_color: u32,
-    current_color: u32,
+    current_color: HdrColor,
+    blend_mode: BlendMode,
 }
 
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer::new_multisampled(width, height, 1)
+    }
+
+    // `samples` must be 1, 2, 4 or 8
+    pub fn new_multisampled(width: usize, height: usize, samples: usize) -> Self {
+        assert!(matches!(samples, 1 | 2 | 4 | 8), "unsupported MSAA mode: {}x", samples);
         Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
-            zbuffer: vec![f32::INFINITY; width * height],
+            hdr_buffer: vec![HdrColor::black(); width * height],
+            emission_buffer: vec![HdrColor::black(); width * height],
+            display_buffer: vec![HdrColor::black(); width * height],
+            depth_buffer: vec![f32::INFINITY; width * height],
+            color_samples: vec![HdrColor::black(); width * height * samples],
+            emission_samples: vec![HdrColor::black(); width * height * samples],
+            zbuffer: vec![f32::INFINITY; width * height * samples],
+            samples,
             background_color: 0x000000,
-            current_color: 0xFFFFFF,
+            current_color: HdrColor::new(1.0, 1.0, 1.0),
+            blend_mode: BlendMode::Replace,
+        }
+    }
+
+    // Reallocates every buffer for a new size, keeping the MSAA mode and the
+    // background color. Nothing drawn before survives.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let background_color = self.background_color;
+        *self = Framebuffer::new_multisampled(width, height, self.samples);
+        self.background_color = background_color;
+    }
+
+    // Sample offsets inside a pixel, in pixel units
+    pub fn sample_positions(&self) -> &'static [(f32, f32)] {
+        match self.samples {
+            2 => &SAMPLES_2X,
+            4 => &SAMPLES_4X,
+            8 => &SAMPLES_8X,
+            _ => &SAMPLES_1X,
         }
     }
 
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+        let background = HdrColor::from(Color::from_hex(self.background_color));
+        for pixel in self.color_samples.iter_mut() {
+            *pixel = background;
+        }
+        for emission in self.emission_samples.iter_mut() {
+            *emission = HdrColor::black();
         }
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
     }
 
+    // Blends the current color into every sample of the pixel that passes the
+    // depth test. Depth is only written in `BlendMode::Replace`.
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+            let first = (y * self.width + x) * self.samples;
+            for index in first..first + self.samples {
+                if self.zbuffer[index] > depth {
+                    self.color_samples[index] = self.color_samples[index].blend(&self.current_color, self.blend_mode);
+                    // The current color is not emissive
+                    let emission = HdrColor::black().with_alpha(self.current_color.a);
+                    self.emission_samples[index] = self.emission_samples[index].blend(&emission, self.blend_mode);
+                    if self.blend_mode == BlendMode::Replace {
+                        self.zbuffer[index] = depth;
+                    }
+                }
             }
         }
     }
 
+    // Averages the samples of every pixel into `hdr_buffer` and `emission_buffer`,
+    // in linear light, and keeps the nearest sample depth in `depth_buffer`
+    pub fn resolve(&mut self) {
+        resolve_samples(&mut self.hdr_buffer, &self.color_samples, self.samples);
+        resolve_samples(&mut self.emission_buffer, &self.emission_samples, self.samples);
+        for (pixel, depths) in self.depth_buffer.iter_mut().zip(self.zbuffer.chunks_exact(self.samples)) {
+            *pixel = depths.iter().copied().fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    // Tone maps `hdr_buffer` into `display_buffer`
+    pub fn tone_map(&mut self, tone_mapping: ToneMapping, exposure: f32) {
+        self.display_buffer
+            .par_iter_mut()
+            .zip(self.hdr_buffer.par_iter())
+            .for_each(|(pixel, &color)| *pixel = tone_mapping.map(color, exposure));
+    }
+
+    // Quantizes `display_buffer` into the `buffer` shown on screen
+    pub fn present(&mut self) {
+        self.buffer
+            .par_iter_mut()
+            .zip(self.display_buffer.par_iter())
+            .for_each(|(pixel, &color)| *pixel = quantize(color).to_hex());
+    }
+
+    // Splits the framebuffer into horizontal tiles that can be shaded in parallel
+    pub fn tiles_mut(&mut self) -> Vec<Tile<'_>> {
+        let width = self.width;
+        let height = self.height;
+        let samples = self.samples;
+        let tile_len = width * TILE_HEIGHT * samples;
+        self.color_samples
+            .chunks_mut(tile_len)
+            .zip(self.emission_samples.chunks_mut(tile_len))
+            .zip(self.zbuffer.chunks_mut(tile_len))
+            .enumerate()
+            .map(|(i, ((color_samples, emission_samples), zbuffer))| {
+                let y_start = i * TILE_HEIGHT;
+                Tile {
+                    width,
+                    y_start,
+                    y_end: (y_start + TILE_HEIGHT).min(height),
+                    samples,
+                    color_samples,
+                    emission_samples,
+                    zbuffer,
+                }
+            })
+            .collect()
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
 
     pub fn set_current_color(&mut self, color: u32) {
+        self.current_color = HdrColor::from(Color::from_hex(color));
+    }
+
+    // Like `set_current_color`, keeping the alpha channel for blending
+    pub fn set_current_rgba(&mut self, color: Color) {
+        self.current_color = HdrColor::from(color);
+    }
+
+    // Same as `set_current_rgba` for a linear HDR color
+    pub fn set_current_hdr(&mut self, color: HdrColor) {
         self.current_color = color;
     }
 
-    pub fn draw_stars(&mut self, stars: &[(usize, usize)]) {
-        for &(x, y) in stars {
-            if x < self.width && y < self.height {
-                let index = y * self.width + x;
-                self.buffer[index] = 0xFFFFFF; // Color blanco para las estrellas
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    // Each star is a `size` x `size` square, with its position scaled by `size`.
+    // Stars are emissive, so they glow once bloom is applied
+    pub fn draw_stars(&mut self, stars: &[(usize, usize)], size: usize) {
+        for &(star_x, star_y) in stars {
+            for y in star_y * size..(star_y + 1) * size {
+                for x in star_x * size..(star_x + 1) * size {
+                    if x < self.width && y < self.height {
+                        let first = (y * self.width + x) * self.samples;
+                        let white = HdrColor::new(1.0, 1.0, 1.0); // Color blanco para las estrellas
+                        self.color_samples[first..first + self.samples].fill(white);
+                        self.emission_samples[first..first + self.samples].fill(white);
+                    }
+                }
             }
         }
     }
 
 }
+
+// Averages each run of `samples` consecutive samples into one pixel
+fn resolve_samples(pixels: &mut [HdrColor], samples: &[HdrColor], count: usize) {
+    if count == 1 {
+        pixels.copy_from_slice(samples);
+        return;
+    }
+
+    let weight = 1.0 / count as f32;
+    for (pixel, colors) in pixels.iter_mut().zip(samples.chunks_exact(count)) {
+        *pixel = colors.iter().fold(HdrColor::black(), |sum, &color| sum + color * weight);
+    }
+}
+
+// A band of rows of the framebuffer with its own slice of the color and depth buffers
+pub struct Tile<'a> {
+    pub width: usize,
+    pub y_start: usize,
+    pub y_end: usize,
+    samples: usize,
+    color_samples: &'a mut [HdrColor],
+    emission_samples: &'a mut [HdrColor],
+    zbuffer: &'a mut [f32],
+}
+
+impl Tile<'_> {
+    fn first_sample(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && (self.y_start..self.y_end).contains(&y) {
+            Some(((y - self.y_start) * self.width + x) * self.samples)
+        } else {
+            None
+        }
+    }
+
+    // Returns the subset of `coverage` whose samples would pass the depth test
+    pub fn depth_test(&self, x: usize, y: usize, coverage: u8, depths: &[f32]) -> u8 {
+        let Some(first) = self.first_sample(x, y) else {
+            return 0;
+        };
+
+        (0..self.samples)
+            .filter(|&s| coverage & (1 << s) != 0 && self.zbuffer[first + s] > depths[s])
+            .fold(0, |mask, s| mask | (1 << s))
+    }
+
+    // Blends the shaded color and emission into every covered sample that passes
+    // the depth test, both weighted by the color's alpha. Depth is only written
+    // in `BlendMode::Replace`.
+    pub fn point(&mut self, x: usize, y: usize, coverage: u8, output: FragmentOutput, depths: &[f32], blend_mode: BlendMode) {
+        let Some(first) = self.first_sample(x, y) else {
+            return;
+        };
+
+        let emission = output.emission.with_alpha(output.color.a);
+        let range = first..first + self.samples;
+        let colors = self.color_samples[range.clone()].iter_mut().zip(self.emission_samples[range.clone()].iter_mut());
+        let zbuffer = &mut self.zbuffer[range];
+        for (s, ((sample_color, sample_emission), (sample_depth, &depth))) in colors.zip(zbuffer.iter_mut().zip(depths)).enumerate() {
+            if coverage & (1 << s) != 0 && *sample_depth > depth {
+                *sample_color = sample_color.blend(&output.color, blend_mode);
+                *sample_emission = sample_emission.blend(&emission, blend_mode);
+                if blend_mode == BlendMode::Replace {
+                    *sample_depth = depth;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::color::{BlendMode, Color};
+use crate::font::{glyph, ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT};
+use crate::framebuffer::Framebuffer;
+
+// Something the HUD draws, positioned in pixels from the top-left corner
+enum Element {
+    Panel { x: i32, y: i32, width: usize, height: usize, color: Color },
+    Text { x: i32, y: i32, text: String, color: Color, scale: usize },
+}
+
+// A flat layer drawn over the finished frame, after tone mapping and the post
+// effects, so it is neither lit nor graded. Elements are queued during the
+// frame and painted in order by `draw`.
+#[derive(Default)]
+pub struct Hud {
+    elements: Vec<Element>,
+}
+
+impl Hud {
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    // A rectangle blended over the image by the alpha of `color`
+    pub fn panel(&mut self, x: i32, y: i32, width: usize, height: usize, color: Color) {
+        self.elements.push(Element::Panel { x, y, width, height, color });
+    }
+
+    // Text with its top-left corner at (x, y); '\n' starts a new line. Every
+    // pixel of the font becomes a `scale` x `scale` square.
+    pub fn text(&mut self, x: i32, y: i32, text: &str, color: Color, scale: usize) {
+        self.elements.push(Element::Text { x, y, text: text.to_string(), color, scale: scale.max(1) });
+    }
+
+    // Paints the queued elements over `Framebuffer::buffer`, after `Framebuffer::present`
+    pub fn draw(&self, framebuffer: &mut Framebuffer) {
+        let mut canvas = Canvas { pixels: &mut framebuffer.buffer, width: framebuffer.width, height: framebuffer.height };
+        for element in &self.elements {
+            match element {
+                Element::Panel { x, y, width, height, color } => canvas.rect(*x, *y, *width, *height, *color),
+                Element::Text { x, y, text, color, scale } => {
+                    for (row, line) in text.lines().enumerate() {
+                        let line_y = y + (row * LINE_HEIGHT * scale) as i32;
+                        for (column, c) in line.chars().enumerate() {
+                            let glyph_x = x + (column * ADVANCE * scale) as i32;
+                            canvas.glyph(glyph_x, line_y, c, *color, *scale);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Width and height in pixels of `text` drawn at `scale`
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let rows = text.lines().count();
+    if columns == 0 || rows == 0 {
+        return (0, 0);
+    }
+    let width = columns * ADVANCE - (ADVANCE - GLYPH_WIDTH);
+    let height = rows * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT);
+    (width * scale, height * scale)
+}
+
+// The presented 0xRRGGBB image, blended in display space
+struct Canvas<'a> {
+    pixels: &'a mut [u32],
+    width: usize,
+    height: usize,
+}
+
+impl Canvas<'_> {
+    fn rect(&mut self, x: i32, y: i32, width: usize, height: usize, color: Color) {
+        let clip = |v: i32, max: usize| v.clamp(0, max as i32) as usize;
+        let (x0, x1) = (clip(x, self.width), clip(x + width as i32, self.width));
+        let (y0, y1) = (clip(y, self.height), clip(y + height as i32, self.height));
+        for row in y0..y1 {
+            for pixel in &mut self.pixels[row * self.width + x0..row * self.width + x1] {
+                *pixel = Color::from_hex(*pixel).blend(&color, BlendMode::Normal).to_hex();
+            }
+        }
+    }
+
+    fn glyph(&mut self, x: i32, y: i32, c: char, color: Color, scale: usize) {
+        for (column, bits) in glyph(c).iter().enumerate() {
+            for row in 0..GLYPH_HEIGHT {
+                if bits >> row & 1 == 1 {
+                    let (px, py) = (x + (column * scale) as i32, y + (row * scale) as i32);
+                    self.rect(px, py, scale, scale, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_size_counts_the_longest_line() {
+        assert_eq!(text_size("", 1), (0, 0));
+        assert_eq!(text_size("ab", 1), (11, 7));
+        assert_eq!(text_size("abc\nd", 2), (34, 34));
+    }
+
+    #[test]
+    fn text_is_drawn_over_panels() {
+        let mut framebuffer = Framebuffer::new(12, 10);
+        let mut hud = Hud::default();
+        hud.panel(0, 0, 12, 10, Color::new_rgba(0, 0, 255, 255));
+        hud.text(1, 1, "|", Color::new(255, 255, 255), 1);
+        hud.draw(&mut framebuffer);
+
+        // '|' is a single full-height column in the middle of the glyph
+        let at = |x: usize, y: usize| framebuffer.buffer[y * 12 + x];
+        assert!((1..8).all(|y| at(3, y) == 0xffffff));
+        assert_eq!(at(2, 4), 0x0000ff);
+        assert_eq!(at(3, 8), 0x0000ff);
+    }
+}
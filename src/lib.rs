@@ -0,0 +1,823 @@
+use nalgebra_glm::{look_at, perspective, Mat4, Vec3, Vec4};
+use std::f32::consts::PI;
+use std::ops::AddAssign;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+pub mod framebuffer;
+pub mod triangle;
+pub mod vertex;
+pub mod model;
+pub mod color;
+pub mod fragment;
+pub mod shaders;
+pub mod camera;
+pub mod clipping;
+pub mod supersample;
+pub mod export;
+pub mod tonemap;
+pub mod bloom;
+pub mod post;
+pub mod effects;
+pub mod line;
+pub mod circle;
+pub mod debug_view;
+pub mod font;
+pub mod hud;
+pub mod culling;
+pub mod sphere;
+pub mod lod;
+pub mod mesh;
+
+use framebuffer::{Framebuffer, TILE_HEIGHT};
+use fragment::{Fragment, FragmentOutput};
+use vertex::Vertex;
+use mesh::Mesh;
+use model::Obj;
+use camera::Camera;
+use color::{BlendMode, HdrColor};
+use triangle::{triangle, wireframe, points, is_culled, calculate_bounding_box, CullMode, FrontFace, RenderMode, POINT_SIZE};
+use shaders::{vertex_shader, screen_mapping, fragment_shader};
+use clipping::clip_triangle_tracked;
+use tonemap::ToneMapping;
+use line::Line;
+use circle::Circle;
+use bloom::Bloom;
+use post::{PostChain, PostFrame};
+use debug_view::DebugView;
+use culling::{BoundingSphere, Frustum};
+use lod::{screen_radius, sphere_lod, Lod};
+use sphere::SphereKind;
+
+#[derive(Clone, Copy)]
+pub struct Uniforms {
+    model_matrix: Mat4,
+    view_matrix: Mat4,
+    projection_matrix: Mat4,
+    viewport_matrix: Mat4,
+    time: u32,
+}
+
+
+
+fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0,  0.0,    0.0,   0.0,
+        0.0,  cos_x, -sin_x, 0.0,
+        0.0,  sin_x,  cos_x, 0.0,
+        0.0,  0.0,    0.0,   1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y,  0.0,  sin_y, 0.0,
+        0.0,    1.0,  0.0,   0.0,
+        -sin_y, 0.0,  cos_y, 0.0,
+        0.0,    0.0,  0.0,   1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0,
+        sin_z,  cos_z, 0.0, 0.0,
+        0.0,    0.0,  1.0, 0.0,
+        0.0,    0.0,  0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale, 0.0,   0.0,   translation.x,
+        0.0,   scale, 0.0,   translation.y,
+        0.0,   0.0,   scale, translation.z,
+        0.0,   0.0,   0.0,   1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+
+fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    look_at(&eye, &center, &up)
+}
+
+// Planos de recorte de la proyección
+pub const NEAR: f32 = 0.1;
+pub const FAR: f32 = 1000.0;
+
+fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
+    let fov = 45.0 * PI / 180.0;
+    let aspect_ratio = window_width / window_height;
+
+    // nalgebra-glm recibe el aspecto antes que el campo de visión, al revés que GLM
+    perspective(aspect_ratio, fov, NEAR, FAR)
+}
+
+fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+    Mat4::new(
+        width / 2.0, 0.0, 0.0, width / 2.0,
+        0.0, -height / 2.0, 0.0, height / 2.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0
+    )
+}
+
+// Semilla fija: el cielo es el mismo en cada ejecución, así las imágenes se pueden reproducir
+const STAR_SEED: u64 = 2024;
+
+fn generate_stars(num_stars: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+    let mut rng = StdRng::seed_from_u64(STAR_SEED);
+    (0..num_stars)
+        .map(|_| {
+            (
+                rng.gen_range(0..width),  // Posición X de la estrella
+                rng.gen_range(0..height) // Posición Y de la estrella
+            )
+        })
+        .collect()
+}
+
+// Cinturón de asteroides entre las órbitas interiores y las exteriores
+const ASTEROID_SEED: u64 = 1801;
+const BELT_RADII: (f32, f32) = (6.5, 7.8);
+
+fn generate_asteroids(count: usize) -> Vec<Instance> {
+    let mut rng = StdRng::seed_from_u64(ASTEROID_SEED);
+    (0..count)
+        .map(|_| {
+            let radius = rng.gen_range(BELT_RADII.0..BELT_RADII.1);
+            let angle = rng.gen_range(0.0..2.0 * PI);
+            Instance {
+                translation: Vec3::new(radius * angle.cos(), rng.gen_range(-0.3..0.3), radius * angle.sin()),
+                rotation: Vec3::new(rng.gen_range(0.0..PI), rng.gen_range(0.0..2.0 * PI), 0.0),
+                scale: rng.gen_range(0.04..0.12),
+                // Los de adentro dan la vuelta más rápido, como en una órbita de Kepler
+                orbit_speed: 0.002 * (BELT_RADII.0 / radius).powf(1.5),
+                spin_speed: rng.gen_range(-0.02..0.02),
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct RenderStats {
+    // Ejecuciones del vertex shader, una por vértice distinto de cada malla dibujada
+    pub vertices_shaded: usize,
+    pub fragments_shaded: usize,
+    pub early_depth_rejected: usize,
+    // Objetos que no se dibujaron por quedar fuera del frustum
+    pub objects_culled: usize,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.vertices_shaded += other.vertices_shaded;
+        self.fragments_shaded += other.fragments_shaded;
+        self.early_depth_rejected += other.early_depth_rejected;
+        self.objects_culled += other.objects_culled;
+    }
+}
+
+// Flat colors of the debug render modes, which skip the fragment shader
+const WIRE_COLOR: HdrColor = HdrColor { r: 0.1, g: 1.0, b: 0.2, a: 1.0 };
+const POINT_COLOR: HdrColor = HdrColor { r: 1.0, g: 0.8, b: 0.1, a: 1.0 };
+
+// A clipped triangle in screen space, with what the debug render modes need to
+// know about the mesh it came from
+struct AssembledTriangle {
+    vertices: [Vertex; 3],
+    // Whether the edges v1-v2, v2-v3 and v3-v1 belong to the mesh
+    edges: [bool; 3],
+    // Transformed vertex at each corner, None where clipping made a new one
+    sources: [Option<usize>; 3],
+    // Copy of the mesh the triangle belongs to, which picks its uniforms
+    instance: usize,
+    // Corners drawn as points: each vertex of the mesh is drawn by the first
+    // triangle that has it
+    points: [bool; 3],
+}
+
+// Draws one copy of `mesh` for every model matrix in `instances`, in that order,
+// with the material of `object`. All copies go through each stage together, so
+// a batch of small objects costs one pass over the tiles instead of one per object.
+// The model matrix of `uniforms` is ignored.
+fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, instances: &[Mat4], object: &SceneObject, mesh: &Mesh, scene: &Scene, stats: &mut RenderStats) {
+    let (mode, view) = (scene.render_mode, scene.debug_view);
+    let instance_uniforms: Vec<Uniforms> = instances
+        .iter()
+        .map(|&model_matrix| Uniforms { model_matrix, ..*uniforms })
+        .collect();
+    let vertex_count = mesh.vertices.len();
+    let triangle_count = mesh.triangle_count();
+
+    // Vertex Shader Stage: each vertex of each copy is transformed once, however
+    // many triangles share it, and the results are kept for primitive assembly
+    let transformed_vertices: Vec<Vertex> = (0..instances.len() * vertex_count)
+        .into_par_iter()
+        .map(|i| vertex_shader(&mesh.vertices[i % vertex_count], &instance_uniforms[i / vertex_count]))
+        .collect();
+    stats.vertices_shaded += transformed_vertices.len();
+
+    // Primitive Assembly Stage, looking up the corners of each triangle by index
+    // among the vertices of its copy
+    let mut triangles: Vec<AssembledTriangle> = (0..instances.len() * triangle_count)
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let (instance, tri) = (i / triangle_count, i % triangle_count);
+            let corners = &mesh.indices[tri * 3..tri * 3 + 3];
+            let sources = [corners[0], corners[1], corners[2]].map(|index| instance * vertex_count + index as usize);
+            let [v1, v2, v3] = sources.map(|source| &transformed_vertices[source]);
+
+            // Clipping Stage
+            let mut polygon = clip_triangle_tracked(v1, v2, v3);
+            for clipped in polygon.iter_mut() {
+                screen_mapping(&mut clipped.vertex, uniforms);
+            }
+
+            // Re-triangulate the clipped polygon as a fan, dropping culled faces. Only
+            // the first and last triangles touch the edges out of the fan's apex.
+            let last = polygon.len().saturating_sub(1);
+            (1..last)
+                .filter(|&i| !is_culled(&polygon[0].vertex, &polygon[i].vertex, &polygon[i + 1].vertex, object.cull_mode, object.front_face))
+                .map(|i| {
+                    let fan = [&polygon[0], &polygon[i], &polygon[i + 1]];
+                    AssembledTriangle {
+                        vertices: fan.map(|clipped| clipped.vertex.clone()),
+                        edges: [i == 1 && polygon[0].original_edge, polygon[i].original_edge, i + 1 == last && polygon[last].original_edge],
+                        sources: fan.map(|clipped| clipped.corner.map(|corner| sources[corner])),
+                        points: [false; 3],
+                        instance,
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    // Each vertex of the mesh is drawn as a point once, by the first triangle that
+    // still has it after clipping and culling
+    if mode == RenderMode::Points {
+        let mut drawn = vec![false; transformed_vertices.len()];
+        for tri in triangles.iter_mut() {
+            tri.points = tri.sources.map(|source| source.is_some_and(|source| !std::mem::replace(&mut drawn[source], true)));
+        }
+    }
+
+    // Binning Stage: every tile keeps, in submission order, the triangles that touch it.
+    // Vertex points may reach past the triangle's bounding box.
+    let margin = if mode == RenderMode::Points { POINT_SIZE / 2 } else { 0 };
+    let sample_positions = framebuffer.sample_positions();
+    let mut tiles = framebuffer.tiles_mut();
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); tiles.len()];
+    for (index, tri) in triangles.iter().enumerate() {
+        let [v1, v2, v3] = &tri.vertices;
+        let (_, min_y, _, max_y) = calculate_bounding_box(
+            &v1.transformed_position,
+            &v2.transformed_position,
+            &v3.transformed_position,
+        );
+        let (min_y, max_y) = (min_y - margin, max_y + margin);
+        let first = (min_y.max(0) as usize / TILE_HEIGHT).min(bins.len());
+        let last = (max_y.max(0) as usize / TILE_HEIGHT).min(bins.len() - 1);
+        for bin in bins.iter_mut().take(last + 1).skip(first) {
+            bin.push(index);
+        }
+    }
+
+    // Rasterization and Fragment Processing Stages, one task per tile. Each tile
+    // sees its triangles in the same order as a serial renderer would, so the
+    // result does not depend on the number of threads.
+    let tile_stats: Vec<RenderStats> = tiles
+        .par_iter_mut()
+        .zip(bins.par_iter())
+        .map(|(tile, bin)| {
+            let mut tile_stats = RenderStats::default();
+            let scissor = (0, tile.y_start as i32, tile.width as i32, tile.y_end as i32);
+
+            // Fragments go straight from the rasterizer to the shader, they are never
+            // stored. Edges and vertices of the debug modes bring their own flat color.
+            let mut process_fragment = |fragment: Fragment, uniforms: &Uniforms, flat_color: Option<HdrColor>| {
+                let x = fragment.position.x as usize;
+                let y = fragment.position.y as usize;
+
+                // Early depth test: skip the shader for fragments that are already hidden
+                let mut coverage = fragment.coverage;
+                if object.early_depth_test {
+                    coverage = tile.depth_test(x, y, coverage, &fragment.sample_depths);
+                    if coverage == 0 {
+                        tile_stats.early_depth_rejected += 1;
+                        return;
+                    }
+                }
+
+                // So do the debug views, which show a fragment attribute instead of shading,
+                // with every object opaque
+                if let Some(color) = flat_color.or_else(|| view.fragment_color(&fragment)) {
+                    tile.point(x, y, coverage, FragmentOutput::from(color), &fragment.sample_depths, BlendMode::Replace);
+                    return;
+                }
+
+                // Apply fragment shader, once per pixel whatever the number of samples
+                let mut output = fragment_shader(&fragment, uniforms, object.id);
+                output.color.a *= object.opacity;
+                tile_stats.fragments_shaded += 1;
+                tile.point(x, y, coverage, output, &fragment.sample_depths, object.blend_mode);
+            };
+
+            for &index in bin {
+                let tri = &triangles[index];
+                let [v1, v2, v3] = &tri.vertices;
+                let uniforms = &instance_uniforms[tri.instance];
+                if matches!(mode, RenderMode::Solid | RenderMode::SolidWireframe) {
+                    triangle(v1, v2, v3, scissor, sample_positions, &mut |f| process_fragment(f, uniforms, None));
+                }
+                if matches!(mode, RenderMode::Wireframe | RenderMode::SolidWireframe) {
+                    wireframe(v1, v2, v3, tri.edges, scissor, sample_positions, &mut |f| process_fragment(f, uniforms, Some(WIRE_COLOR)));
+                }
+                if mode == RenderMode::Points {
+                    points(v1, v2, v3, tri.points, scissor, sample_positions, &mut |f| process_fragment(f, uniforms, Some(POINT_COLOR)));
+                }
+            }
+
+            tile_stats
+        })
+        .collect();
+
+    for tile_stat in tile_stats {
+        *stats += tile_stat;
+    }
+}
+
+
+
+pub struct SceneObject {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: f32,
+    // Una o más teselaciones del modelo; cada cuadro se usa la que corresponde
+    // a su tamaño en pantalla. Los objetos con el mismo modelo comparten una sola
+    // copia de los vértices.
+    pub mesh: Lod,
+    // Esfera que contiene al modelo, en sus coordenadas, para descartarlo si queda fuera de la cámara
+    pub bounds: BoundingSphere,
+    pub id: f32, 
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    // Si es false el shader se ejecuta siempre, aunque el fragmento quede oculto
+    pub early_depth_test: bool,
+    // Cualquier modo distinto de Replace hace al objeto transparente: se dibuja
+    // después de los opacos, de atrás hacia adelante y sin escribir profundidad
+    pub blend_mode: BlendMode,
+    // Multiplica el alfa que devuelve el shader
+    pub opacity: f32,
+}
+
+// Una copia de un objeto instanciado, que gira alrededor del eje Y y sobre sí misma
+#[derive(Clone, Copy)]
+pub struct Instance {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: f32,
+    // Radianes por paso de simulación de la órbita y del giro propio
+    pub orbit_speed: f32,
+    pub spin_speed: f32,
+}
+
+// Muchas copias de un objeto que comparten modelo, shader y material, como los
+// asteroides de un cinturón. La posición, rotación y escala de `object` no se
+// usan: salen de cada instancia.
+pub struct InstancedObject {
+    pub object: SceneObject,
+    pub instances: Vec<Instance>,
+}
+
+pub struct Scene {
+    pub objects: Vec<SceneObject>,
+    // Grupos de objetos instanciados, que se dibujan con los opacos
+    pub instanced: Vec<InstancedObject>,
+    pub nave: SceneObject,
+    // false en la vista de sky eye, donde la nave no se dibuja
+    pub show_nave: bool,
+    pub stars: Vec<(usize, usize)>,
+    // Relleno, aristas, vértices o relleno con aristas, para depurar la geometría
+    pub render_mode: RenderMode,
+    // Órbitas, ejes y vectores de velocidad
+    pub show_guides: bool,
+    // Imagen sombreada, o profundidad, normales, UV o posiciones para depurar
+    pub debug_view: DebugView,
+    // Ángulo y velocidad orbital de cada objeto, en el mismo orden que `objects`
+    pub orbit_angles: Vec<f32>,
+    pub orbit_speeds: Vec<f32>,
+}
+
+impl Scene {
+    // Reparte de nuevo las estrellas en un área de `width` x `height` píxeles;
+    // con la semilla fija, el cielo de cada tamaño es siempre el mismo
+    pub fn scatter_stars(&mut self, width: usize, height: usize) {
+        self.stars = generate_stars(self.stars.len(), width, height);
+    }
+
+    // Avanza la simulación un frame: cada objeto gira alrededor del eje Y
+    pub fn step(&mut self) {
+        for (i, object) in self.objects.iter_mut().enumerate() {
+            // Índice de rotación
+            let angle = self.orbit_angles[i];
+
+            // Matriz de rotación en torno al origen
+            let rotation_matrix = Mat4::new_rotation(Vec3::new(0.0, angle, 0.0));
+
+            // Transforma la posición del objeto
+            let rotated_translation = rotation_matrix * Vec4::new(object.translation.x, object.translation.y, object.translation.z, 1.0);
+
+            // Actualiza la posición del objeto
+            object.translation = Vec3::new(rotated_translation.x, rotated_translation.y, rotated_translation.z);
+
+            // Incrementa el ángulo para el próximo frame
+            self.orbit_angles[i] = (self.orbit_angles[i] + self.orbit_speeds[i]) % (2.0 * PI);
+        }
+
+        for instance in self.instanced.iter_mut().flat_map(|group| group.instances.iter_mut()) {
+            let rotated = Mat4::new_rotation(Vec3::new(0.0, instance.orbit_speed, 0.0)).transform_point(&instance.translation.into());
+            instance.translation = rotated.coords;
+            instance.rotation.y = (instance.rotation.y + instance.spin_speed) % (2.0 * PI);
+        }
+    }
+}
+
+// Colores de las guías
+const ORBIT_COLOR: HdrColor = HdrColor { r: 0.3, g: 0.35, b: 0.45, a: 0.8 };
+const VELOCITY_COLOR: HdrColor = HdrColor { r: 1.0, g: 0.6, b: 0.1, a: 1.0 };
+const AXIS_COLORS: [HdrColor; 3] = [
+    HdrColor { r: 1.0, g: 0.1, b: 0.1, a: 1.0 },
+    HdrColor { r: 0.1, g: 1.0, b: 0.1, a: 1.0 },
+    HdrColor { r: 0.1, g: 0.3, b: 1.0, a: 1.0 },
+];
+
+// Dibuja la órbita de cada planeta alrededor del eje Y, los ejes de coordenadas
+// en el origen y la dirección en la que avanza cada planeta
+fn draw_guides(framebuffer: &mut Framebuffer, scene: &Scene, uniforms: &Uniforms) {
+    let axis_length = 6.0;
+    for (axis, color) in AXIS_COLORS.iter().enumerate() {
+        let mut end = Vec3::zeros();
+        end[axis] = axis_length;
+        framebuffer.line(Vec3::zeros(), end, *color, uniforms);
+    }
+
+    for object in &scene.objects {
+        let position = object.translation;
+        let radius = (position.x * position.x + position.z * position.z).sqrt();
+        if radius < 1e-3 {
+            continue; // El sol está en el centro
+        }
+
+        framebuffer.circle(Vec3::new(0.0, position.y, 0.0), radius, ORBIT_COLOR, uniforms);
+
+        // La rotación alrededor de +Y mueve cada planeta en la dirección (z, 0, -x)
+        let direction = Vec3::new(position.z, 0.0, -position.x) / radius;
+        framebuffer.line(position, position + direction * 2.0, VELOCITY_COLOR, uniforms);
+    }
+}
+
+// Dibuja la escena completa en el framebuffer. Las matrices de proyección y de
+// viewport salen del tamaño del framebuffer, así que sirve igual para la ventana
+// que para imágenes más grandes; `star_size` es el lado en píxeles de cada estrella.
+pub fn render_scene(framebuffer: &mut Framebuffer, scene: &Scene, camera: &Camera, time: u32, star_size: usize) -> RenderStats {
+    let mut stats = RenderStats::default();
+
+    // Las vistas de depuración no llevan cielo
+    if scene.debug_view == DebugView::Shaded {
+        framebuffer.draw_stars(&scene.stars, star_size);
+    }
+
+    // Calcula las matrices de cámara
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+    // Uniformes comunes a todos los objetos; la matriz del modelo va aparte
+    let uniforms = Uniforms { model_matrix: Mat4::identity(), view_matrix, projection_matrix, viewport_matrix, time };
+
+    // Un objeto cuya esfera envolvente queda fuera de alguno de los seis planos
+    // de la cámara no se ve, así que ni siquiera pasa por el vertex shader. Para
+    // los demás se devuelve el radio en píxeles de la esfera, que elige el nivel de detalle.
+    let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+    let height = framebuffer.height as f32;
+    let visible = |object: &SceneObject, model_matrix: &Mat4, stats: &mut RenderStats| {
+        let bounds = object.bounds.transform(model_matrix);
+        if !frustum.intersects(&bounds) {
+            stats.objects_culled += 1;
+            return None;
+        }
+        Some(screen_radius(&bounds, &view_matrix, &projection_matrix, height))
+    };
+
+    // Renderizar la nave
+    if scene.show_nave {
+        let nave = &scene.nave;
+        let model_matrix = create_model_matrix(nave.translation, nave.scale, nave.rotation);
+        if let Some(radius) = visible(nave, &model_matrix, &mut stats) {
+            render(framebuffer, &uniforms, &[model_matrix], nave, nave.mesh.select(radius), scene, &mut stats);
+        }
+    }
+
+    let distance = |object: &SceneObject| (object.translation - camera.eye).magnitude();
+    let (mut opaque, mut transparent): (Vec<&SceneObject>, Vec<&SceneObject>) = scene
+        .objects
+        .iter()
+        .partition(|object| object.blend_mode == BlendMode::Replace);
+
+    // Los opacos se dibujan de adelante hacia atrás para que la prueba de
+    // profundidad temprana descarte la mayor cantidad de fragmentos ocultos
+    opaque.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+
+    // Los transparentes van al final y de atrás hacia adelante, para que cada uno
+    // se mezcle sobre lo que ya quedó detrás
+    transparent.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+    let draw = |framebuffer: &mut Framebuffer, object: &SceneObject, stats: &mut RenderStats| {
+        // Crea la matriz del modelo del objeto
+        let model_matrix = create_model_matrix(object.translation, object.scale, object.rotation);
+        let Some(radius) = visible(object, &model_matrix, stats) else {
+            return;
+        };
+
+        // Renderiza el objeto
+        render(framebuffer, &uniforms, &[model_matrix], object, object.mesh.select(radius), scene, stats);
+    };
+
+    for object in opaque {
+        draw(framebuffer, object, &mut stats);
+    }
+
+    // Los grupos instanciados van con los opacos. Cada copia visible se suma a la
+    // tanda de su nivel de detalle, de adelante hacia atrás, y cada tanda pasa una
+    // sola vez por el pipeline; los niveles finos, que son los cercanos, primero.
+    for group in &scene.instanced {
+        let lod = &group.object.mesh;
+        let mut batches: Vec<Vec<(f32, Mat4)>> = vec![Vec::new(); lod.levels().len()];
+        for instance in &group.instances {
+            let model_matrix = create_model_matrix(instance.translation, instance.scale, instance.rotation);
+            if let Some(radius) = visible(&group.object, &model_matrix, &mut stats) {
+                let distance = (instance.translation - camera.eye).magnitude();
+                batches[lod.level_for(radius)].push((distance, model_matrix));
+            }
+        }
+
+        for (level, mut batch) in batches.into_iter().enumerate().rev() {
+            if batch.is_empty() {
+                continue;
+            }
+            batch.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let matrices: Vec<Mat4> = batch.into_iter().map(|(_, model_matrix)| model_matrix).collect();
+            render(framebuffer, &uniforms, &matrices, &group.object, &lod.levels()[level].mesh, scene, &mut stats);
+        }
+    }
+
+    // Las guías se mezclan sobre lo opaco, antes de los anillos transparentes
+    if scene.show_guides {
+        draw_guides(framebuffer, scene, &uniforms);
+    }
+
+    for object in transparent {
+        draw(framebuffer, object, &mut stats);
+    }
+
+    stats
+}
+
+// Ajustes de la etapa final, que pasa de luz lineal a la imagen que se muestra
+#[derive(Clone, Copy)]
+pub struct Presentation {
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    // Resplandor de los cuerpos emisivos, `None` si está apagado
+    pub bloom: Option<Bloom>,
+}
+
+// Lo que se ve al iniciar, sin opciones: clamp, sin exposición extra y sin bloom
+impl Default for Presentation {
+    fn default() -> Self {
+        Presentation { tone_mapping: ToneMapping::Clamp, exposure: 1.0, bloom: None }
+    }
+}
+
+// Combina las muestras, añade el bloom, aplica el tone mapping y los efectos
+// activos, dejando la imagen final en `buffer`
+pub fn finish_frame(framebuffer: &mut Framebuffer, presentation: &Presentation, post: &PostChain, time: u32) {
+    framebuffer.resolve();
+    if let Some(bloom) = &presentation.bloom {
+        bloom.apply(framebuffer);
+    }
+    framebuffer.tone_map(presentation.tone_mapping, presentation.exposure);
+    post.apply(&mut PostFrame::new(framebuffer, time));
+    framebuffer.present();
+}
+
+// En lugar de `finish_frame` para las vistas de depuración: los valores se
+// muestran tal cual, sin bloom, tone mapping ni efectos
+pub fn finish_debug_frame(framebuffer: &mut Framebuffer, view: DebugView) {
+    framebuffer.resolve();
+    view.display(framebuffer);
+    framebuffer.present();
+}
+
+// Radio de `assets/sphere.obj`, al que están ajustadas las escalas y los shaders
+// de los planetas
+pub const SPHERE_RADIUS: f32 = 0.5;
+
+// Carga los modelos y arma la escena inicial; las estrellas se reparten en un
+// área de `width` x `height` píxeles. Los planetas y el sol son esferas
+// generadas del tipo `sphere`, con varios niveles de detalle, y el cinturón
+// tiene `asteroids` copias de la misma esfera.
+pub fn load_scene(width: usize, height: usize, sphere: SphereKind, asteroids: usize) -> Scene {
+    let sphere_mesh = sphere_lod(sphere, SPHERE_RADIUS);
+    let sphere_bounds = sphere_mesh.bounding_sphere();
+    let obj2 = Obj::load("assets/rings.obj").expect("Failed to load obj");
+    let nave_obj = Obj::load("assets/nave.obj").expect("Failed to load obj");
+
+    let objects = vec![
+        // sol
+        SceneObject {
+            translation: Vec3::new(0.0, 0.0, 0.0),
+            rotation: Vec3::new(0.0, 0.0, 0.0),
+            scale: 3.0,
+            mesh: sphere_mesh.clone(),
+            bounds: sphere_bounds,
+            id: 3.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+        // luna
+        SceneObject {
+            translation: Vec3::new(6.5, 7.5, 0.0),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 0.3,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 2.0, 
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+        
+        SceneObject {
+            translation: Vec3::new(6.0, 7.0, 0.0),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 1.0,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 1.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+       
+        SceneObject {
+            translation: Vec3::new(8.0, 6.0, 7.0),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 1.0,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 4.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+
+        SceneObject {
+            translation: Vec3::new(-8.0, 0.0, -2.3),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 0.7,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 5.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+
+        SceneObject {
+            translation: Vec3::new(-4.0, -1.0, -2.3),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 0.7,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 6.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+        
+
+        SceneObject {
+            translation: Vec3::new(-5.3, 5.0, 7.3),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 1.3,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 8.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+
+        SceneObject {
+            translation: Vec3::new(4.3, 1.0, -3.3),
+            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
+            scale: 1.7,
+            mesh: sphere_mesh.clone(), // Reutilizando el mismo modelo
+            bounds: sphere_bounds,
+            id: 6.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+        SceneObject {
+            translation: Vec3::new(4.3, 1.0, -3.3),
+            rotation: Vec3::new(0.5, PI / 4.0, 0.0),
+            scale: 0.8,
+            mesh: Lod::single(obj2.get_mesh()),
+            bounds: obj2.bounding_sphere(),
+            id: 5.0,
+            cull_mode: CullMode::None,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Normal,
+            opacity: 0.6,
+        },
+    ];
+
+    // model position
+    let nave = SceneObject {
+        translation: Vec3::new(0.0, 0.0, 10.0),
+        rotation: Vec3::new(0.0, -5.0, 0.0),
+        scale: 0.1,
+        mesh: Lod::single(nave_obj.get_mesh()),
+        bounds: nave_obj.bounding_sphere(),
+        id: 1.0,
+        // La nave no tiene un orden de vértices consistente, así que se dibuja por ambos lados
+        cull_mode: CullMode::None,
+        front_face: FrontFace::CounterClockwise,
+        early_depth_test: true,
+        blend_mode: BlendMode::Replace,
+        opacity: 1.0,
+    };
+
+    let belt = InstancedObject {
+        object: SceneObject {
+            translation: Vec3::zeros(),
+            rotation: Vec3::zeros(),
+            scale: 1.0,
+            mesh: sphere_mesh.clone(),
+            bounds: sphere_bounds,
+            id: 2.0,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            early_depth_test: true,
+            blend_mode: BlendMode::Replace,
+            opacity: 1.0,
+        },
+        instances: generate_asteroids(asteroids),
+    };
+
+    Scene {
+        objects,
+        instanced: vec![belt],
+        nave,
+        show_nave: true,
+        stars: generate_stars(500, width, height),
+        render_mode: RenderMode::Solid,
+        debug_view: DebugView::Shaded,
+        show_guides: false,
+        // Ángulos de rotación
+        orbit_angles: vec![0.0; 9],
+        orbit_speeds: vec![0.000001, 0.000002, 0.000002, 0.000001, 0.000002, 0.000005,0.000001, 0.000002, 0.000002],
+    }
+}
+
+pub fn default_camera() -> Camera {
+    // camera parameters
+    Camera::new(
+        Vec3::new(0.0, 0.0, 20.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0)
+    )
+}
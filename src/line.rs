@@ -0,0 +1,266 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4, dot};
+use crate::fragment::Fragment;
+use crate::vertex::Vertex;
+use crate::color::{BlendMode, Color, HdrColor};
+use crate::framebuffer::{Framebuffer, MAX_SAMPLES};
+use crate::clipping::clip_line;
+use crate::shaders::screen_mapping;
+use crate::Uniforms;
+
+pub trait Line {
+    // Draws a world-space segment through the view, projection and viewport
+    // matrices of `uniforms` (the model matrix is ignored), clipped to the
+    // frustum and depth-tested. The line is opaque and writes depth.
+    fn line(&mut self, start: Vec3, end: Vec3, color: HdrColor, uniforms: &Uniforms);
+
+    // Like `line`, antialiased: blended over the image by pixel coverage,
+    // without writing depth, so it belongs after the opaque geometry
+    fn smooth_line(&mut self, start: Vec3, end: Vec3, color: HdrColor, uniforms: &Uniforms);
+}
+
+impl Line for Framebuffer {
+    fn line(&mut self, start: Vec3, end: Vec3, color: HdrColor, uniforms: &Uniforms) {
+        let Some((a, b)) = project_segment(start, end, uniforms) else {
+            return;
+        };
+
+        self.set_current_hdr(color);
+        self.set_blend_mode(BlendMode::Replace);
+        let scissor = (0, 0, self.width as i32, self.height as i32);
+        rasterize_line(&a, &b, scissor, 1, &mut |fragment: Fragment| {
+            self.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+        });
+    }
+
+    fn smooth_line(&mut self, start: Vec3, end: Vec3, color: HdrColor, uniforms: &Uniforms) {
+        let Some((a, b)) = project_segment(start, end, uniforms) else {
+            return;
+        };
+
+        self.set_blend_mode(BlendMode::Normal);
+        rasterize_smooth_line(a.transformed_position, b.transformed_position, &mut |x, y, depth, coverage| {
+            if x >= 0 && y >= 0 {
+                self.set_current_hdr(color.with_alpha(color.a * coverage));
+                self.point(x as usize, y as usize, depth);
+            }
+        });
+    }
+}
+
+// Takes a world-space segment to screen space, or None if it is outside the frustum
+fn project_segment(start: Vec3, end: Vec3, uniforms: &Uniforms) -> Option<(Vertex, Vertex)> {
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let to_clip = |position: Vec3| {
+        let mut vertex = Vertex::new(position, Vec3::zeros(), Vec2::zeros());
+        vertex.clip_position = view_projection * Vec4::new(position.x, position.y, position.z, 1.0);
+        vertex
+    };
+
+    let (mut a, mut b) = clip_line(&to_clip(start), &to_clip(end))?;
+    screen_mapping(&mut a, uniforms);
+    screen_mapping(&mut b, uniforms);
+    Some((a, b))
+}
+
+// Rasterizes the segment between two screen-space vertices with a DDA, one
+// fragment per pixel. Depth is interpolated linearly in screen space and the
+// other attributes perspective-correctly. Every fragment covers the samples in
+// `coverage`, and only pixels inside `scissor` (min_x, min_y, max_x, max_y, max
+// exclusive) are visited.
+pub fn rasterize_line<F: FnMut(Fragment)>(a: &Vertex, b: &Vertex, scissor: (i32, i32, i32, i32), coverage: u8, emit: &mut F) {
+    let (pa, pb) = (a.transformed_position, b.transformed_position);
+    let steps = (pb.x - pa.x).abs().max((pb.y - pa.y).abs()).ceil().max(1.0) as usize;
+
+    // Only walk the steps inside the scissor. The steps are still those of the
+    // whole segment, so a line split across tiles lands on the same pixels.
+    let Some((t0, t1)) = scissor_range(pa, pb, scissor) else {
+        return;
+    };
+    let first = ((t0 * steps as f32).floor() as usize).saturating_sub(1);
+    let last = ((t1 * steps as f32).ceil() as usize + 1).min(steps);
+
+    let mut last_pixel = None;
+    for i in first..=last {
+        let t = i as f32 / steps as f32;
+        let position = pa + (pb - pa) * t;
+        let pixel = (position.x.floor() as i32, position.y.floor() as i32);
+        if last_pixel == Some(pixel) {
+            continue;
+        }
+        last_pixel = Some(pixel);
+
+        // The range is widened by a step on each side against rounding, so its
+        // ends may fall just outside
+        let (x, y) = pixel;
+        if x < scissor.0 || y < scissor.1 || x >= scissor.2 || y >= scissor.3 {
+            continue;
+        }
+
+        // Perspective-correct weights of the two endpoints
+        let (p1, p2) = ((1.0 - t) * a.inv_w, t * b.inv_w);
+        let (w1, w2) = (p1 / (p1 + p2), p2 / (p1 + p2));
+
+        let normal = (a.transformed_normal * w1 + b.transformed_normal * w2).normalize();
+        let vertex_position = a.position * w1 + b.position * w2;
+        let tex_coords = a.tex_coords * w1 + b.tex_coords * w2;
+        emit(fragment_at(x, y, position.z, normal, vertex_position, tex_coords, coverage));
+    }
+}
+
+// Parameters along `a` to `b` between which the segment is inside `scissor`
+// (Liang-Barsky), or None if it misses the scissor entirely
+fn scissor_range(a: Vec3, b: Vec3, scissor: (i32, i32, i32, i32)) -> Option<(f32, f32)> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    let bounds = [
+        (a.x, b.x, scissor.0 as f32, scissor.2 as f32),
+        (a.y, b.y, scissor.1 as f32, scissor.3 as f32),
+    ];
+    for (start, end, min, max) in bounds {
+        let delta = end - start;
+        if delta == 0.0 {
+            if start < min || start >= max {
+                return None;
+            }
+            continue;
+        }
+        let (enter, exit) = ((min - start) / delta, (max - start) / delta);
+        let (enter, exit) = if delta > 0.0 { (enter, exit) } else { (exit, enter) };
+        t0 = t0.max(enter);
+        t1 = t1.min(exit);
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+// Xiaolin Wu's antialiased line between two screen-space points. `emit` gets
+// every touched pixel with its depth, interpolated linearly in screen space,
+// and the fraction of the pixel covered by a one pixel wide line.
+pub fn rasterize_smooth_line<F: FnMut(i32, i32, f32, f32)>(a: Vec3, b: Vec3, emit: &mut F) {
+    // Pixel centers on integer coordinates
+    let (mut x0, mut y0, mut x1, mut y1) = (a.x - 0.5, a.y - 0.5, b.x - 0.5, b.y - 0.5);
+    let (mut z0, mut z1) = (a.z, b.z);
+
+    // Walk along the major axis, left to right
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep {
+        std::mem::swap(&mut x0, &mut y0);
+        std::mem::swap(&mut x1, &mut y1);
+    }
+    if x0 > x1 {
+        std::mem::swap(&mut x0, &mut x1);
+        std::mem::swap(&mut y0, &mut y1);
+        std::mem::swap(&mut z0, &mut z1);
+    }
+
+    let dx = x1 - x0;
+    let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+    let depth_at = |x: f32| if dx == 0.0 { z0 } else { z0 + (z1 - z0) * ((x - x0) / dx).clamp(0.0, 1.0) };
+    let mut plot = |major: i32, minor: i32, depth: f32, coverage: f32| {
+        if coverage > 0.0 {
+            if steep { emit(minor, major, depth, coverage) } else { emit(major, minor, depth, coverage) }
+        }
+    };
+
+    // Endpoints only cover part of their pixel along the major axis
+    let mut endpoint = |x: f32, y: f32, gap: f32| {
+        let x_end = x.round();
+        let y_end = y + gradient * (x_end - x);
+        let (px, py) = (x_end as i32, y_end.floor() as i32);
+        let depth = depth_at(x_end);
+        plot(px, py, depth, (1.0 - fpart(y_end)) * gap);
+        plot(px, py + 1, depth, fpart(y_end) * gap);
+        (px, y_end)
+    };
+    let (first, first_y) = endpoint(x0, y0, 1.0 - fpart(x0 + 0.5));
+    let (last, _) = endpoint(x1, y1, fpart(x1 + 0.5));
+
+    let mut y = first_y + gradient;
+    for x in first + 1..last {
+        let depth = depth_at(x as f32);
+        plot(x, y.floor() as i32, depth, 1.0 - fpart(y));
+        plot(x, y.floor() as i32 + 1, depth, fpart(y));
+        y += gradient;
+    }
+}
+
+// Fractional part, also in [0, 1) for negative numbers
+fn fpart(x: f32) -> f32 {
+    x - x.floor()
+}
+
+// A fragment for pixel (x, y) carrying the attributes of a single vertex
+pub fn vertex_fragment(vertex: &Vertex, x: i32, y: i32, coverage: u8) -> Fragment {
+    fragment_at(
+        x,
+        y,
+        vertex.transformed_position.z,
+        vertex.transformed_normal.normalize(),
+        vertex.position,
+        vertex.tex_coords,
+        coverage,
+    )
+}
+
+fn fragment_at(x: i32, y: i32, depth: f32, normal: Vec3, vertex_position: Vec3, tex_coords: Vec2, coverage: u8) -> Fragment {
+    let intensity = dot(&normal, &Vec3::new(0.0, 0.0, 1.0)).max(0.0);
+    let mut fragment = Fragment::new(
+        Vec2::new(x as f32, y as f32),
+        Color::new(100, 100, 100),
+        depth,
+        normal,
+        intensity,
+        vertex_position,
+        tex_coords,
+    );
+    fragment.coverage = coverage;
+    fragment.sample_depths = [depth; MAX_SAMPLES];
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_line_spreads_one_pixel_of_coverage_per_column() {
+        let mut columns = [0.0; 40];
+        rasterize_smooth_line(Vec3::new(3.5, 10.2, 0.0), Vec3::new(33.5, 21.7, 1.0), &mut |x, _, _, coverage| {
+            columns[x as usize] += coverage;
+        });
+
+        for (x, &total) in columns.iter().enumerate() {
+            if (4..33).contains(&x) {
+                assert!((total - 1.0).abs() < 1e-4, "column {} has coverage {}", x, total);
+            } else if !(3..=33).contains(&x) {
+                assert_eq!(total, 0.0, "column {} is outside the line", x);
+            }
+        }
+    }
+
+    #[test]
+    fn line_split_across_tiles_covers_the_same_pixels() {
+        let vertex = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+        let (a, b) = (vertex(-20.3, 75.8), vertex(130.6, -9.2));
+        let pixels = |scissor| {
+            let mut pixels = Vec::new();
+            rasterize_line(&a, &b, scissor, 1, &mut |f: Fragment| pixels.push((f.position.x as i32, f.position.y as i32)));
+            pixels
+        };
+
+        let whole = pixels((0, 0, 100, 64));
+        assert!(!whole.is_empty());
+        let tiled: Vec<_> = (0..4).flat_map(|tile| pixels((0, tile * 16, 100, tile * 16 + 16))).collect();
+        let sorted = |mut pixels: Vec<(i32, i32)>| {
+            pixels.sort_unstable();
+            pixels
+        };
+        assert_eq!(sorted(whole), sorted(tiled));
+        assert!(pixels((0, 70, 100, 80)).is_empty());
+    }
+
+    #[test]
+    fn smooth_line_interpolates_depth() {
+        let mut depths = Vec::new();
+        rasterize_smooth_line(Vec3::new(0.5, 0.5, 0.0), Vec3::new(10.5, 0.5, 1.0), &mut |x, _, depth, _| depths.push((x, depth)));
+        assert!(depths.iter().all(|&(x, depth)| (depth - x as f32 / 10.0).abs() < 1e-6));
+    }
+}
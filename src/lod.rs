@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use nalgebra_glm::{Mat4, Vec4};
+use crate::culling::BoundingSphere;
+use crate::sphere::{icosphere, uv_sphere, SphereKind};
+use crate::mesh::Mesh;
+
+// One tessellation of a model, used once the model covers at least
+// `min_screen_radius` pixels
+pub struct LodLevel {
+    pub min_screen_radius: f32,
+    pub mesh: Mesh,
+}
+
+// A handle to the levels of detail of a model, from the coarsest to the finest.
+// Clones share the same meshes, so any number of objects can draw one model
+// while its vertices are stored once.
+#[derive(Clone)]
+pub struct Lod {
+    levels: Arc<[LodLevel]>,
+}
+
+impl Lod {
+    pub fn new(mut levels: Vec<LodLevel>) -> Self {
+        assert!(!levels.is_empty(), "a model needs at least one level of detail");
+        levels.sort_by(|a, b| a.min_screen_radius.total_cmp(&b.min_screen_radius));
+        Lod { levels: levels.into() }
+    }
+
+    // A model drawn with the same triangles at every size
+    pub fn single(mesh: Mesh) -> Self {
+        Lod::new(vec![LodLevel { min_screen_radius: 0.0, mesh }])
+    }
+
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    // Whether both handles point to the same meshes
+    pub fn shares_meshes(&self, other: &Lod) -> bool {
+        Arc::ptr_eq(&self.levels, &other.levels)
+    }
+
+    // Index of the finest level allowed at `screen_radius`, or of the coarsest
+    // one when the model is smaller than all of them
+    pub fn level_for(&self, screen_radius: f32) -> usize {
+        self.levels.iter().rposition(|level| level.min_screen_radius <= screen_radius).unwrap_or(0)
+    }
+
+    pub fn select(&self, screen_radius: f32) -> &Mesh {
+        &self.levels[self.level_for(screen_radius)].mesh
+    }
+
+    // Sphere around every vertex of every level, in model coordinates
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(
+            self.levels.iter().flat_map(|level| level.mesh.vertices.iter().map(|vertex| &vertex.position)),
+        )
+    }
+}
+
+// Screen-space radius thresholds of the sphere levels, coarsest first
+const SPHERE_RADII: [f32; 4] = [0.0, 12.0, 48.0, 160.0];
+
+// Spheres from 80 to a few thousand triangles, stepping up as they cover more
+// of the screen
+pub fn sphere_lod(kind: SphereKind, radius: f32) -> Lod {
+    let levels = SPHERE_RADII
+        .iter()
+        .enumerate()
+        .map(|(level, &min_screen_radius)| {
+            let mesh = match kind {
+                SphereKind::Uv => uv_sphere(radius, 6 << level, 12 << level),
+                SphereKind::Ico => icosphere(radius, level + 1),
+            };
+            LodLevel { min_screen_radius, mesh }
+        })
+        .collect();
+    Lod::new(levels)
+}
+
+// Approximate radius in pixels of a world-space sphere on a viewport `height`
+// pixels tall. Spheres that reach the camera are infinitely large.
+pub fn screen_radius(sphere: &BoundingSphere, view: &Mat4, projection: &Mat4, height: f32) -> f32 {
+    let center = view * Vec4::new(sphere.center.x, sphere.center.y, sphere.center.z, 1.0);
+    let depth = -center.z;
+    if depth <= sphere.radius {
+        return f32::INFINITY;
+    }
+    sphere.radius / depth * projection[(1, 1)] * height / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{look_at, perspective, Vec3};
+
+    #[test]
+    fn finer_levels_are_picked_as_the_model_grows() {
+        let lod = sphere_lod(SphereKind::Ico, 1.0);
+        assert_eq!(lod.levels().len(), 4);
+        assert_eq!(lod.level_for(2.0), 0);
+        assert_eq!(lod.level_for(12.0), 1);
+        assert_eq!(lod.level_for(100.0), 2);
+        assert_eq!(lod.level_for(f32::INFINITY), 3);
+        assert!(lod.select(1.0).triangle_count() < lod.select(1000.0).triangle_count());
+        assert!((lod.bounding_sphere().radius - 1.0).abs() < 1e-3);
+
+        let copy = lod.clone();
+        assert!(copy.shares_meshes(&lod));
+        assert!(!copy.shares_meshes(&sphere_lod(SphereKind::Ico, 1.0)));
+    }
+
+    #[test]
+    fn screen_radius_shrinks_with_distance() {
+        let view = look_at(&Vec3::new(0.0, 0.0, 10.0), &Vec3::zeros(), &Vec3::new(0.0, 1.0, 0.0));
+        // A vertical field of view of 90 degrees spans `height` pixels at distance 1
+        let projection = perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 1000.0);
+        let sphere = |z: f32| BoundingSphere { center: Vec3::new(0.0, 0.0, z), radius: 1.0 };
+
+        assert!((screen_radius(&sphere(0.0), &view, &projection, 600.0) - 30.0).abs() < 1e-3);
+        assert!((screen_radius(&sphere(-10.0), &view, &projection, 600.0) - 15.0).abs() < 1e-3);
+        assert_eq!(screen_radius(&sphere(9.5), &view, &projection, 600.0), f32::INFINITY);
+    }
+}
@@ -1,367 +1,523 @@
-use nalgebra_glm::{look_at, perspective, Mat4, Vec3, Vec4};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::Vec3;
+use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
 use std::f32::consts::PI;
-use rand::Rng;
-
-mod framebuffer;
-mod triangle;
-mod vertex;
-mod model;
-mod color;
-mod fragment;
-mod shaders;
-mod camera;
-
-use framebuffer::Framebuffer;
-use vertex::Vertex;
-use model::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
-
-pub struct Uniforms {
-    model_matrix: Mat4,
-    view_matrix: Mat4,
-    projection_matrix: Mat4,
-    viewport_matrix: Mat4,
-    time: u32,
+use std::time::Instant;
+use image::ImageResult;
+
+use proyecto3::{default_camera, finish_debug_frame, finish_frame, load_scene, render_scene, Presentation, RenderStats, Scene};
+use proyecto3::framebuffer::Framebuffer;
+use proyecto3::camera::Camera;
+use proyecto3::supersample::{render_supersampled, DownsampleFilter};
+use proyecto3::export::{save_image, FrameWriter};
+use proyecto3::tonemap::ToneMapping;
+use proyecto3::bloom::Bloom;
+use proyecto3::post::PostChain;
+use proyecto3::effects::{ChromaticAberration, ColorGrading, FilmGrain, Lut3d, Vignette};
+use proyecto3::debug_view::DebugView;
+use proyecto3::sphere::SphereKind;
+use proyecto3::hud::{text_size, Hud};
+use proyecto3::color::Color;
+
+// Efectos de postproceso en el orden en que se aplican; solo los pedidos con `--post` empiezan activos
+fn create_post_chain(options: &Options) -> PostChain {
+    let lut = match &options.lut {
+        Some(path) => Lut3d::load_cube(path).unwrap_or_else(|err| {
+            eprintln!("No se pudo leer la LUT {}: {}", path, err);
+            Lut3d::warm()
+        }),
+        None => Lut3d::warm(),
+    };
+
+    let mut chain = PostChain::default();
+    chain.push(ChromaticAberration { strength: 0.004 }, false);
+    chain.push(ColorGrading { lut }, false);
+    chain.push(Vignette { strength: 0.6, radius: 0.4 }, false);
+    chain.push(FilmGrain { amount: 0.04 }, false);
+
+    for name in &options.post {
+        if !chain.set_enabled(name, true) {
+            eprintln!("Efecto desconocido: {}", name);
+        }
+    }
+    chain
 }
 
-
-
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
-
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
-
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
-
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
-
-    let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
-    );
-
-    transform_matrix * rotation_matrix
+// Opciones de línea de comandos
+struct Options {
+    // Muestras de MSAA por píxel: `--msaa 1|2|4|8`
+    msaa_samples: usize,
+    // Factor y filtro de las capturas con la tecla P: `--supersample N --filter box|tent|lanczos`
+    supersample: usize,
+    filter: DownsampleFilter,
+    // Tone mapping, exposición y bloom iniciales: `--tonemap clamp|reinhard|aces --exposure F --bloom on|off`
+    presentation: Presentation,
+    // Efectos activos al iniciar y LUT para el color grading: `--post vignette,grain --lut archivo.cube`
+    post: Vec<String>,
+    lut: Option<String>,
+    // Render sin ventana: `--output imagen.png --time N --eye x,y,z --center x,y,z`
+    output: Option<String>,
+    time: u32,
+    eye: Option<Vec3>,
+    center: Option<Vec3>,
+    // Vista inicial, también sin ventana: `--view shaded|depth|normal|uv|position`
+    view: DebugView,
+    // Teselación de los planetas: `--sphere uv|ico`
+    sphere: SphereKind,
+    // Copias de la esfera en el cinturón de asteroides: `--asteroids N`. Sin
+    // cinturón por defecto
+    asteroids: usize,
+    // Tamaño de la imagen sin ventana, o tamaño inicial de la ventana: `--size 800x600`
+    size: (usize, usize),
+    // Fracción de la resolución de la ventana a la que se dibuja, luego escalada: `--render-scale 0.5`
+    render_scale: f32,
+    // Tamaño de la pantalla completa (F11): `--screen 1920x1080`
+    screen: (usize, usize),
+    // Grabación de `--frames N` cuadros, avanzando `--step K` pasos de simulación
+    // entre cuadros; `--fps F` fija la velocidad del GIF
+    frames: Option<u32>,
+    step: u32,
+    fps: u32,
 }
 
+// "x,y,z" -> Vec3
+fn parse_vec3(text: &str) -> Option<Vec3> {
+    let values: Vec<f32> = text.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, z] => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
 
-fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
-    look_at(&eye, &center, &up)
+// "800x600" -> (800, 600)
+fn parse_size(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = text.split_once('x')?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+        _ => None,
+    }
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
-    let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
+fn parse_options(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        msaa_samples: 1,
+        supersample: 4,
+        filter: DownsampleFilter::Lanczos,
+        presentation: Presentation::default(),
+        post: Vec::new(),
+        lut: None,
+        output: None,
+        time: 0,
+        eye: None,
+        center: None,
+        size: (800, 600),
+        view: DebugView::Shaded,
+        sphere: SphereKind::Uv,
+        asteroids: 0,
+        render_scale: 1.0,
+        screen: (1920, 1080),
+        frames: None,
+        step: 1,
+        fps: 30,
+    };
+
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        match (arg.as_str(), value.as_deref()) {
+            ("--msaa", Some("1")) => options.msaa_samples = 1,
+            ("--msaa", Some("2")) => options.msaa_samples = 2,
+            ("--msaa", Some("4")) => options.msaa_samples = 4,
+            ("--msaa", Some("8")) => options.msaa_samples = 8,
+            ("--supersample", Some(factor)) => match factor.parse::<usize>() {
+                Ok(factor) if factor >= 1 => options.supersample = factor,
+                _ => eprintln!("Valor de --supersample no válido: {}", factor),
+            },
+            ("--filter", Some("box")) => options.filter = DownsampleFilter::Box,
+            ("--filter", Some("tent")) => options.filter = DownsampleFilter::Tent,
+            ("--filter", Some("lanczos")) => options.filter = DownsampleFilter::Lanczos,
+            ("--tonemap", Some("clamp")) => options.presentation.tone_mapping = ToneMapping::Clamp,
+            ("--tonemap", Some("reinhard")) => options.presentation.tone_mapping = ToneMapping::Reinhard,
+            ("--tonemap", Some("aces")) => options.presentation.tone_mapping = ToneMapping::Aces,
+            ("--bloom", Some("on")) => options.presentation.bloom = Some(Bloom::default()),
+            ("--bloom", Some("off")) => options.presentation.bloom = None,
+            ("--post", Some(names)) => options.post.extend(names.split(',').map(|name| name.trim().to_string())),
+            ("--lut", Some(path)) => options.lut = Some(path.to_string()),
+            ("--output", Some(path)) => options.output = Some(path.to_string()),
+            ("--time", Some(time)) => match time.parse::<u32>() {
+                Ok(time) => options.time = time,
+                _ => eprintln!("Valor de --time no válido: {}", time),
+            },
+            ("--eye", Some(eye)) => match parse_vec3(eye) {
+                Some(eye) => options.eye = Some(eye),
+                None => eprintln!("Valor de --eye no válido: {}", eye),
+            },
+            ("--center", Some(center)) => match parse_vec3(center) {
+                Some(center) => options.center = Some(center),
+                None => eprintln!("Valor de --center no válido: {}", center),
+            },
+            ("--size", Some(size)) => match parse_size(size) {
+                Some(size) => options.size = size,
+                None => eprintln!("Valor de --size no válido: {}", size),
+            },
+            ("--view", Some("shaded")) => options.view = DebugView::Shaded,
+            ("--view", Some("depth")) => options.view = DebugView::Depth,
+            ("--view", Some("normal")) => options.view = DebugView::Normal,
+            ("--view", Some("uv")) => options.view = DebugView::TexCoords,
+            ("--view", Some("position")) => options.view = DebugView::Position,
+            ("--sphere", Some("uv")) => options.sphere = SphereKind::Uv,
+            ("--sphere", Some("ico")) => options.sphere = SphereKind::Ico,
+            ("--asteroids", Some(count)) => match count.parse::<usize>() {
+                Ok(count) => options.asteroids = count,
+                _ => eprintln!("Valor de --asteroids no válido: {}", count),
+            },
+            ("--render-scale", Some(scale)) => match scale.parse::<f32>() {
+                Ok(scale) if scale > 0.0 && scale <= 1.0 => options.render_scale = scale,
+                _ => eprintln!("Valor de --render-scale no válido: {}", scale),
+            },
+            ("--screen", Some(size)) => match parse_size(size) {
+                Some(size) => options.screen = size,
+                None => eprintln!("Valor de --screen no válido: {}", size),
+            },
+            ("--frames", Some(frames)) => match frames.parse::<u32>() {
+                Ok(frames) if frames > 0 => options.frames = Some(frames),
+                _ => eprintln!("Valor de --frames no válido: {}", frames),
+            },
+            ("--step", Some(step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => options.step = step,
+                _ => eprintln!("Valor de --step no válido: {}", step),
+            },
+            ("--fps", Some(fps)) => match fps.parse::<u32>() {
+                Ok(fps) if fps > 0 => options.fps = fps,
+                _ => eprintln!("Valor de --fps no válido: {}", fps),
+            },
+            ("--exposure", Some(exposure)) => match exposure.parse::<f32>() {
+                Ok(exposure) if exposure > 0.0 => options.presentation.exposure = exposure,
+                _ => eprintln!("Valor de --exposure no válido: {}", exposure),
+            },
+            (name, value) => eprintln!("Opción no soportada: {} {:?}", name, value),
+        }
+    }
 
-    perspective(fov, aspect_ratio, near, far)
+    options
 }
 
-fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
-    Mat4::new(
-        width / 2.0, 0.0, 0.0, width / 2.0,
-        0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
-        0.0, 0.0, 0.0, 1.0
-    )
+// Termina el frame con el tone mapping y los efectos, o tal cual en las vistas de depuración
+fn finish(framebuffer: &mut Framebuffer, scene: &Scene, presentation: &Presentation, post: &PostChain, time: u32) {
+    match scene.debug_view {
+        DebugView::Shaded => finish_frame(framebuffer, presentation, post, time),
+        view => finish_debug_frame(framebuffer, view),
+    }
 }
 
-fn generate_stars(num_stars: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
-    let mut rng = rand::thread_rng();
-    (0..num_stars)
-        .map(|_| {
-            (
-                rng.gen_range(0..width),  // Posición X de la estrella
-                rng.gen_range(0..height) // Posición Y de la estrella
-            )
-        })
-        .collect()
+// Cámara de los renders sin ventana, movida con `--eye` y `--center`
+fn headless_camera(options: &Options) -> Camera {
+    let mut camera = default_camera();
+    if let Some(eye) = options.eye {
+        camera.eye = eye;
+    }
+    if let Some(center) = options.center {
+        camera.center = center;
+    }
+    camera
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], id: f32) {
-    
-    // Vertex Shader Stage
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+// Renderiza sin ventana el frame número `options.time` y lo guarda en `path`
+// (PNG, JPEG, ... según la extensión)
+fn render_headless(options: &Options, path: &str) -> ImageResult<()> {
+    let (width, height) = options.size;
+    let mut scene = load_scene(width, height, options.sphere, options.asteroids);
+    scene.debug_view = options.view;
+    for _ in 0..options.time {
+        scene.step();
     }
 
-    // Primitive Assembly Stage
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
-        }
-    }
+    let mut framebuffer = Framebuffer::new_multisampled(width, height, options.msaa_samples);
+    framebuffer.clear();
+    render_scene(&mut framebuffer, &scene, &headless_camera(options), options.time, 1);
+    finish(&mut framebuffer, &scene, &options.presentation, &create_post_chain(options), options.time);
 
-    // Rasterization Stage
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
+    save_image(path, &framebuffer.buffer, width, height)
+}
 
-    // Fragment Processing Stage
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            // Apply fragment shader
-            // if id == 0.0{
-            //     let color = fragment.color.to_hex();
-            //     framebuffer.set_current_color(color);
-            //     framebuffer.point(x, y, fragment.depth);
-            // }else{
-                let shaded_color = fragment_shader(&fragment, &uniforms, id);
-                let color = shaded_color.to_hex();
-                framebuffer.set_current_color(color);
-                framebuffer.point(x, y, fragment.depth);
-            // }
-        }
-    }
+// Frame de la simulación que corresponde al cuadro `frame` de una grabación,
+// o None si no cabe en un u32
+fn frame_time(options: &Options, frame: u32) -> Option<u32> {
+    frame.checked_mul(options.step)?.checked_add(options.time)
 }
 
+// Graba `frames` cuadros sin ventana a partir del frame `options.time`,
+// avanzando la simulación `options.step` pasos fijos entre uno y otro, así
+// que el resultado es el mismo en cada corrida
+fn record(options: &Options, path: &str, frames: u32) -> ImageResult<()> {
+    let (width, height) = options.size;
+    let mut scene = load_scene(width, height, options.sphere, options.asteroids);
+    scene.debug_view = options.view;
+    let camera = headless_camera(options);
+    let post = create_post_chain(options);
+    let mut framebuffer = Framebuffer::new_multisampled(width, height, options.msaa_samples);
+    let mut writer = FrameWriter::create(path, frames as usize, options.fps)?;
+
+    let mut time = 0;
+    for frame in 0..frames {
+        // `main` ya comprobó que el último cuadro no se pasa de u32
+        let frame_time = frame_time(options, frame).expect("frame time overflows u32");
+        while time < frame_time {
+            scene.step();
+            time += 1;
+        }
 
+        framebuffer.clear();
+        render_scene(&mut framebuffer, &scene, &camera, time, 1);
+        finish(&mut framebuffer, &scene, &options.presentation, &post, time);
+        writer.write(&framebuffer.buffer, width, height)?;
+    }
 
-pub struct SceneObject {
-    pub translation: Vec3,
-    pub rotation: Vec3,
-    pub scale: f32,
-    pub vertex_array: Vec<Vertex>,
-    pub id: f32, 
+    Ok(())
 }
 
-
 fn main() {
+    let options = parse_options(std::env::args().skip(1));
+
+    // Sin ventana: simula hasta `--time`, guarda la imagen (o la grabación con
+    // `--frames`) y termina
+    if let Some(path) = &options.output {
+        if let Some(frames) = options.frames {
+            if frame_time(&options, frames - 1).is_none() {
+                eprintln!("--time + (--frames - 1) * --step no cabe en un u32 ({})", u32::MAX);
+                std::process::exit(1);
+            }
+        }
+        let (result, saved) = match options.frames {
+            Some(frames) => (record(&options, path, frames), "Grabación guardada en"),
+            None => (render_headless(&options, path), "Imagen guardada en"),
+        };
+        match result {
+            Ok(()) => println!("{} {}", saved, path),
+            Err(err) => {
+                eprintln!("No se pudo guardar {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let obj = Obj::load("assets/sphere.obj").expect("Failed to load obj");
-    let obj2 = Obj::load("assets/rings.obj").expect("Failed to load obj");
-    let nave = Obj::load("assets/nave.obj").expect("Failed to load obj");
     let mut eye = false; 
 
-    let mut objects = vec![
-        // sol
-        SceneObject {
-            translation: Vec3::new(0.0, 0.0, 0.0),
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            scale: 3.0,
-            vertex_array: obj.get_vertex_array(),
-            id: 3.0,
-        },
-        // luna
-        SceneObject {
-            translation: Vec3::new(6.5, 7.5, 0.0),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 0.3,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 2.0, 
-        },
-        
-        SceneObject {
-            translation: Vec3::new(6.0, 7.0, 0.0),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 1.0,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 1.0,
-        },
-       
-        SceneObject {
-            translation: Vec3::new(8.0, 6.0, 7.0),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 1.0,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 4.0,
-        },
-
-        SceneObject {
-            translation: Vec3::new(-8.0, 0.0, -2.3),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 0.7,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 5.0,
-        },
-
-        SceneObject {
-            translation: Vec3::new(-4.0, -1.0, -2.3),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 0.7,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 6.0,
-        },
-        
-
-        SceneObject {
-            translation: Vec3::new(-5.3, 5.0, 7.3),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 1.3,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 8.0,
-        },
-
-        SceneObject {
-            translation: Vec3::new(4.3, 1.0, -3.3),
-            rotation: Vec3::new(0.0, PI / 4.0, 0.0),
-            scale: 1.7,
-            vertex_array: obj.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 6.0,
-        },
-        SceneObject {
-            translation: Vec3::new(4.3, 1.0, -3.3),
-            rotation: Vec3::new(0.5, PI / 4.0, 0.0),
-            scale: 0.8,
-            vertex_array: obj2.get_vertex_array(), // Reutilizando el mismo modelo
-            id: 5.0,
-        },
-    ];
-
-
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let stars = generate_stars(500, framebuffer_width, framebuffer_height);
-
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
-        "Sistema solar",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    )
-    .unwrap();
-
-    window.set_position(500, 500);
+    let mut fullscreen = false;
+    let mut windowed_size = options.size;
+    let mut window = create_window(windowed_size, fullscreen);
     window.update();
 
+    let (framebuffer_width, framebuffer_height) = render_size(window.get_size(), options.render_scale);
+    let mut framebuffer = Framebuffer::new_multisampled(framebuffer_width, framebuffer_height, options.msaa_samples);
     framebuffer.set_background_color(0x000000);
 
-
-    let vertex_arrays_nave = nave.get_vertex_array(); 
-
-
-    // camera parameters
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 20.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0)
-    );
-
-
-        // model position
-    let mut translation = Vec3::new(0.0, 0.0, 10.0);
-    let mut rotation = Vec3::new(0.0, -5.0, 0.0);
-    let scale = 0.1;
+    let mut camera = default_camera();
+    let mut scene = load_scene(framebuffer_width, framebuffer_height, options.sphere, options.asteroids);
+    scene.debug_view = options.view;
 
     let mut time = 0;
 
-    // Ángulos de rotación
-    let mut angles = vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
-    let rotation_speeds = vec![0.000001, 0.000002, 0.000002, 0.000001, 0.000002, 0.000005,0.000001, 0.000002, 0.000002];
-
     let mut mouse_activado= false; 
+    let mut presentation = options.presentation;
+    let mut post = create_post_chain(&options);
+
+    let mut hud = Hud::default();
+    let mut show_hud = true;
+    let mut last_frame = Instant::now();
+    let mut frame_ms = 0.0;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
+
+        // Tiempo de frame suavizado, para que el número del HUD se pueda leer
+        let now = Instant::now();
+        let elapsed_ms = now.duration_since(last_frame).as_secs_f32() * 1000.0;
+        frame_ms = if frame_ms == 0.0 { elapsed_ms } else { frame_ms + (elapsed_ms - frame_ms) * 0.1 };
+        last_frame = now;
+
+        // F11 alterna entre ventana y pantalla completa
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            fullscreen = !fullscreen;
+            if fullscreen {
+                windowed_size = window.get_size();
+            }
+            window = create_window(if fullscreen { options.screen } else { windowed_size }, fullscreen);
+        }
+
+        // El framebuffer sigue el tamaño de la ventana, escalado por `--render-scale`
+        let (width, height) = render_size(window.get_size(), options.render_scale);
+        if (width, height) != (framebuffer.width, framebuffer.height) {
+            framebuffer.resize(width, height);
+            scene.scatter_stars(width, height);
+        }
     
         time += 1;
     
         // Manejo de entrada (teclas para mover la cámara)
-        eye = handle_input(&window, &mut camera, &mut translation, &mut rotation, &mut eye, &mut mouse_activado);
+        eye = handle_input(&window, &mut camera, &mut scene.nave.translation, &mut scene.nave.rotation, &mut eye, &mut mouse_activado);
+        scene.show_nave = !eye;
     
-        // Limpia el framebuffer para el siguiente frame
+        // Avanza las órbitas un paso
+        scene.step();
+
+        // Limpia el framebuffer para el siguiente frame y dibuja la escena
         framebuffer.clear();
-        framebuffer.draw_stars(&stars); 
-        // Calcula las matrices de cámara
-        let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-        let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-    
-        // Renderizar la nave
-        let model_matrix_nave = create_model_matrix(translation, scale, rotation);
-        let uniforms_nave = Uniforms { model_matrix: model_matrix_nave, view_matrix, projection_matrix, viewport_matrix, time };
-        if !eye{
-            render(&mut framebuffer, &uniforms_nave, &vertex_arrays_nave, 1.0);
+        let stats = render_scene(&mut framebuffer, &scene, &camera, time, 1);
+
+        // Exposición con + y -, en cuartos de paso; T cambia de tone mapping
+        if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes) {
+            presentation.exposure *= 2.0_f32.powf(0.25);
         }
-    
-        // Renderizar los objetos con rotación orbital
-        for (i, object) in objects.iter_mut().enumerate() {
-            // Índice de rotación
-            let angle = angles[i];
-    
-            // Matriz de rotación en torno al origen
-            let rotation_matrix = Mat4::new_rotation(Vec3::new(0.0, angle, 0.0));
-    
-            // Transforma la posición del objeto
-            let rotated_translation = rotation_matrix * Vec4::new(object.translation.x, object.translation.y, object.translation.z, 1.0);
-    
-            // Actualiza la posición del objeto
-            object.translation = Vec3::new(rotated_translation.x, rotated_translation.y, rotated_translation.z);
-    
-            // Incrementa el ángulo para el próximo frame
-            angles[i] = (angles[i] + rotation_speeds[i]) % (2.0 * PI);
-    
-            // Crea la matriz del modelo del objeto
-            let model_matrix = create_model_matrix(object.translation, object.scale, object.rotation);
-            // Define los uniformes
-            let uniforms = Uniforms {
-                model_matrix,
-                view_matrix,
-                projection_matrix,
-                viewport_matrix,
-                time,
+        if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes) {
+            presentation.exposure /= 2.0_f32.powf(0.25);
+        }
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            presentation.tone_mapping = presentation.tone_mapping.next();
+        }
+        // F1 a F4 encienden y apagan los efectos de postproceso, en orden
+        for (index, key) in [Key::F1, Key::F2, Key::F3, Key::F4].into_iter().enumerate().take(post.len()) {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                post.toggle(index);
+            }
+        }
+        // R cambia entre relleno, aristas, vértices y relleno con aristas
+        if window.is_key_pressed(Key::R, KeyRepeat::No) {
+            scene.render_mode = scene.render_mode.next();
+        }
+        // V cambia entre la imagen sombreada y las vistas de profundidad, normales, UV y posiciones
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            scene.debug_view = scene.debug_view.next();
+        }
+        // H muestra u oculta el HUD
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            show_hud = !show_hud;
+        }
+        // G muestra u oculta órbitas, ejes y vectores de velocidad
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            scene.show_guides = !scene.show_guides;
+        }
+        // B enciende y apaga el bloom
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            presentation.bloom = match presentation.bloom {
+                Some(_) => None,
+                None => Some(Bloom::default()),
             };
-    
-            // Renderiza el objeto
-            render(&mut framebuffer, &uniforms, &object.vertex_array, object.id);
+        }
+
+        // Captura con supermuestreo, a la resolución del framebuffer
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            let path = format!("captura_{}.png", time);
+            let factor = options.supersample;
+            let (capture_width, capture_height) = (framebuffer.width, framebuffer.height);
+            let pixels = render_supersampled(capture_width, capture_height, factor, options.filter, |fb| {
+                render_scene(fb, &scene, &camera, time, factor);
+                finish(fb, &scene, &presentation, &post, time);
+            });
+            match save_image(&path, &pixels, capture_width, capture_height) {
+                Ok(()) => println!("Captura guardada en {}", path),
+                Err(err) => eprintln!("No se pudo guardar {}: {}", path, err),
+            }
         }
     
-        // Actualiza la ventana con el framebuffer
-        window.update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height).unwrap();
+        window.set_title(&format!(
+            "Sistema solar - fragmentos sombreados: {} - descartados por profundidad: {} - objetos fuera de cámara: {} - {} x{:.2} - {} - {} - {}x{}",
+            stats.fragments_shaded, stats.early_depth_rejected, stats.objects_culled, presentation.tone_mapping.name(), presentation.exposure,
+            scene.render_mode.name(), scene.debug_view.name(), framebuffer.width, framebuffer.height
+        ));
+
+        // Combina las muestras de MSAA, aplica el tone mapping y actualiza la ventana
+        finish(&mut framebuffer, &scene, &presentation, &post, time);
+        if show_hud {
+            update_hud(&mut hud, &camera, &scene, &presentation, &post, &stats, FrameTiming { time, frame_ms });
+            hud.draw(&mut framebuffer);
+        }
+        window.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
     }
     
 }
 
 
 
+// Número del frame que muestra el HUD y cuánto tardó en dibujarse
+struct FrameTiming {
+    time: u32,
+    frame_ms: f32,
+}
+
+// Llena el HUD con el tiempo de frame, el modo de la cámara, el estado del
+// sky eye, los objetos descartados, los modos de dibujo, el tone mapping y los
+// efectos encendidos, sobre un panel en la esquina superior izquierda
+fn update_hud(hud: &mut Hud, camera: &Camera, scene: &Scene, presentation: &Presentation, post: &PostChain, stats: &RenderStats, timing: FrameTiming) {
+    let FrameTiming { time, frame_ms } = timing;
+    let center = camera.center;
+    // Con el sky eye la nave no se dibuja
+    let sky_eye = !scene.show_nave;
+    let effects = post.enabled().collect::<Vec<_>>().join(", ");
+    let text = format!(
+        "Frame {}  {:.1} ms ({:.0} fps)\nCámara: órbita en ({:.1}, {:.1}, {:.1}) a {:.1}\nSky eye: {}\nObjetos fuera de cámara: {}\nVértices sombreados: {}\nDibujo: {} - vista {}\nTone mapping: {} x{:.2}\nEfectos (F1-F4): {}",
+        time,
+        frame_ms,
+        1000.0 / frame_ms.max(1e-3),
+        center.x,
+        center.y,
+        center.z,
+        (camera.eye - center).magnitude(),
+        if sky_eye { "activado" } else { "desactivado" },
+        stats.objects_culled,
+        stats.vertices_shaded,
+        scene.render_mode.name(),
+        scene.debug_view.name(),
+        presentation.tone_mapping.name(),
+        presentation.exposure,
+        if effects.is_empty() { "ninguno" } else { &effects },
+    );
+
+    let (margin, padding) = (8, 6);
+    let (width, height) = text_size(&text, 1);
+    hud.clear();
+    hud.panel(margin, margin, width + 2 * padding as usize, height + 2 * padding as usize, Color::new_rgba(0, 0, 0, 160));
+    hud.text(margin + padding, margin + padding, &text, Color::new(230, 230, 230), 1);
+}
+
+// Crea la ventana, redimensionable y con la imagen estirada a su tamaño. minifb
+// no puede cambiar el modo de video ni preguntar el tamaño del monitor, así que
+// la pantalla completa es una ventana sin bordes, encima de las demás, que
+// ocupa `size` desde la esquina superior izquierda.
+fn create_window(size: (usize, usize), fullscreen: bool) -> Window {
+    let options = WindowOptions {
+        borderless: fullscreen,
+        title: !fullscreen,
+        resize: !fullscreen,
+        topmost: fullscreen,
+        scale_mode: ScaleMode::Stretch,
+        ..WindowOptions::default()
+    };
+    let mut window = Window::new("Sistema solar", size.0, size.1, options).unwrap();
+    if fullscreen {
+        window.set_position(0, 0);
+    } else {
+        window.set_position(500, 500);
+    }
+    window
+}
+
+// Tamaño del framebuffer para una ventana de `window_size` con `--render-scale`;
+// nunca cero, aunque la ventana esté minimizada
+fn render_size(window_size: (usize, usize), scale: f32) -> (usize, usize) {
+    let scaled = |side: usize| ((side as f32 * scale).round() as usize).max(1);
+    (scaled(window_size.0), scaled(window_size.1))
+}
+
 fn handle_input(
     window: &Window,
     camera: &mut Camera,
     translation: &mut Vec3,
     rotation: &mut Vec3,
     eye: &mut bool,
-    mouse: &mut bool, 
+    _mouse: &mut bool, 
 )-> bool {
-    let move_speed = 0.5;    // Velocidad de movimiento de la cámara
     let movement_speed = PI/150.0;
     let rotation_speed = PI/150.0;
     let zoom_speed = 0.03;
@@ -387,8 +543,8 @@ fn handle_input(
     
         if let (Some(last_x), Some(last_y)) = (last_mouse_x, last_mouse_y) {
             // Calcular el movimiento relativo del mouse
-            let delta_x = (current_x as f32 - last_x as f32) * sensitivity;
-            let delta_y = (current_y as f32 - last_y as f32) * sensitivity;
+            let delta_x = (current_x - last_x as f32) * sensitivity;
+            let delta_y = (current_y - last_y as f32) * sensitivity;
     
                 // Rotar la cámara horizontalmente (giro en el eje Y)
                 rotation.y += delta_x;
@@ -412,10 +568,6 @@ fn handle_input(
                     rotation.x += PI / 100.0;
                 }
         }
-    
-        // Actualizar la última posición del mouse
-        last_mouse_x = Some(current_x as f64);
-        last_mouse_y = Some(current_y as f64);
     }
     
     
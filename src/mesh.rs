@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use crate::culling::BoundingSphere;
+use crate::vertex::Vertex;
+
+// An indexed triangle list: every three entries of `indices` are the corners of
+// a triangle, so a vertex shared by several triangles is stored and shaded once
+#[derive(Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        assert_eq!(indices.len() % 3, 0, "indices must come in triangles");
+        assert!(indices.iter().all(|&index| (index as usize) < vertices.len()), "index out of range");
+        Mesh { vertices, indices }
+    }
+
+    // A triangle soup, with every three vertices forming a triangle and nothing shared
+    pub fn unindexed(vertices: Vec<Vertex>) -> Self {
+        let indices = (0..vertices.len() as u32).collect();
+        Mesh::new(vertices, indices)
+    }
+
+    // A triangle soup with the vertices that match in position, normal and texture
+    // coordinates merged into one
+    pub fn welded(triangles: &[Vertex]) -> Self {
+        let key = |vertex: &Vertex| {
+            let (p, n, uv) = (vertex.position, vertex.normal, vertex.tex_coords);
+            [p.x, p.y, p.z, n.x, n.y, n.z, uv.x, uv.y].map(f32::to_bits)
+        };
+
+        let mut vertices = Vec::new();
+        let mut seen = HashMap::new();
+        let indices = triangles
+            .iter()
+            .map(|vertex| {
+                *seen.entry(key(vertex)).or_insert_with(|| {
+                    vertices.push(vertex.clone());
+                    vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+        Mesh::new(vertices, indices)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // Sphere around every vertex, in model coordinates
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(self.vertices.iter().map(|vertex| &vertex.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
+
+    #[test]
+    fn welding_shares_matching_vertices() {
+        let vertex = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::z(), Vec2::new(x, y));
+        // Two triangles of a quad, sharing the diagonal
+        let soup = [vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(1.0, 1.0), vertex(0.0, 0.0), vertex(1.0, 1.0), vertex(0.0, 1.0)];
+
+        let mesh = Mesh::welded(&soup);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(Mesh::unindexed(soup.to_vec()).vertices.len(), 6);
+    }
+}
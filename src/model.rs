@@ -1,16 +1,11 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::mesh::Mesh;
+use crate::culling::BoundingSphere;
 
 pub struct Obj {
-    meshes: Vec<Mesh>,
-}
-
-struct Mesh {
-    vertices: Vec<Vec3>,
-    normals: Vec<Vec3>,
-    texcoords: Vec<Vec2>,
-    indices: Vec<u32>,
+    mesh: Mesh,
+    bounds: BoundingSphere,
 }
 
 impl Obj {
@@ -21,42 +16,44 @@ impl Obj {
             ..Default::default()
         })?;
 
-        let meshes = models.into_iter().map(|model| {
+        // Every model of the file goes into one mesh, with its indices moved past
+        // the vertices of the models before it
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for model in models {
             let mesh = model.mesh;
-            Mesh {
-                vertices: mesh.positions.chunks(3)
-                    .map(|v| Vec3::new(v[0], v[1], v[2]))
-                    .collect(),
-                normals: mesh.normals.chunks(3)
+            let offset = vertices.len() as u32;
+            for (i, position) in mesh.positions.chunks(3).enumerate() {
+                let position = Vec3::new(position[0], position[1], position[2]);
+                let normal = mesh.normals.get(i * 3..i * 3 + 3)
                     .map(|n| Vec3::new(n[0], n[1], n[2]))
-                    .collect(),
-                texcoords: mesh.texcoords.chunks(2)
-                    .map(|t| Vec2::new(t[0], 1.0 - t[1]))
-                    .collect(),
-                indices: mesh.indices,
-            }
-        }).collect();
-
-        Ok(Obj { meshes })
-    }
-
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
-
-        for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
+                let tex_coords = mesh.texcoords.get(i * 2..i * 2 + 2)
+                    .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
+            indices.extend(mesh.indices.iter().map(|&index| index + offset));
         }
 
-        vertices
+        let mesh = Mesh::new(vertices, indices);
+        let bounds = mesh.bounding_sphere();
+        Ok(Obj { mesh, bounds })
+    }
+
+    // Sphere around every vertex, in model coordinates
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.bounds
+    }
+
+    // The model as loaded, with vertices shared between triangles
+    pub fn get_mesh(&self) -> Mesh {
+        self.mesh.clone()
     }
-}
\ No newline at end of file
+
+    // Three vertices per triangle, copied once for every triangle that uses them
+    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+        self.mesh.indices.iter().map(|&index| self.mesh.vertices[index as usize].clone()).collect()
+    }
+}
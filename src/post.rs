@@ -0,0 +1,119 @@
+use crate::color::HdrColor;
+use crate::framebuffer::Framebuffer;
+
+// What a post effect sees of the frame: tone mapped colors in [0, 1] that it
+// may rewrite, and the resolved depth of every pixel (infinite where nothing
+// was drawn)
+pub struct PostFrame<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub color: &'a mut [HdrColor],
+    // None of the built-in effects read depth yet
+    pub depth: &'a [f32],
+    // Frame number, for effects that animate
+    pub time: u32,
+}
+
+impl<'a> PostFrame<'a> {
+    // Must be called after `Framebuffer::tone_map`
+    pub fn new(framebuffer: &'a mut Framebuffer, time: u32) -> Self {
+        PostFrame {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            color: &mut framebuffer.display_buffer,
+            depth: &framebuffer.depth_buffer,
+            time,
+        }
+    }
+}
+
+// A full-screen pass that runs after tone mapping and before `Framebuffer::present`
+pub trait PostEffect: Sync {
+    fn name(&self) -> &str;
+    fn apply(&self, frame: &mut PostFrame);
+}
+
+// Effects applied in insertion order, each of which can be switched on and off
+#[derive(Default)]
+pub struct PostChain {
+    effects: Vec<(Box<dyn PostEffect>, bool)>,
+}
+
+impl PostChain {
+    pub fn push(&mut self, effect: impl PostEffect + 'static, enabled: bool) {
+        self.effects.push((Box::new(effect), enabled));
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    // Flips the effect at `index` and returns its name and new state
+    pub fn toggle(&mut self, index: usize) -> Option<(&str, bool)> {
+        let (effect, enabled) = self.effects.get_mut(index)?;
+        *enabled = !*enabled;
+        Some((effect.name(), *enabled))
+    }
+
+    // Names of the effects that `apply` runs, in order
+    pub fn enabled(&self) -> impl Iterator<Item = &str> {
+        self.effects.iter().filter(|(_, enabled)| *enabled).map(|(effect, _)| effect.name())
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.effects.iter_mut().find(|(effect, _)| effect.name() == name) {
+            Some(entry) => {
+                entry.1 = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn apply(&self, frame: &mut PostFrame) {
+        for (effect, _) in self.effects.iter().filter(|(_, enabled)| *enabled) {
+            effect.apply(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fill(&'static str, f32);
+
+    impl PostEffect for Fill {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn apply(&self, frame: &mut PostFrame) {
+            frame.color.fill(HdrColor::new(self.1, self.1, self.1));
+        }
+    }
+
+    #[test]
+    fn enabled_effects_run_in_order() {
+        let mut chain = PostChain::default();
+        chain.push(Fill("first", 0.25), true);
+        chain.push(Fill("second", 0.5), true);
+        chain.push(Fill("third", 0.75), false);
+
+        let mut color = vec![HdrColor::black(); 4];
+        let depth = vec![f32::INFINITY; 4];
+        let mut frame = PostFrame { width: 2, height: 2, color: &mut color, depth: &depth, time: 0 };
+        chain.apply(&mut frame);
+        assert_eq!(frame.color[0].r, 0.5);
+
+        assert_eq!(chain.toggle(1), Some(("second", false)));
+        assert!(chain.set_enabled("third", true));
+        assert_eq!(chain.enabled().collect::<Vec<_>>(), ["first", "third"]);
+        chain.apply(&mut frame);
+        assert_eq!(frame.color[0].r, 0.75);
+    }
+}
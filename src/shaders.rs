@@ -1,8 +1,8 @@
-use nalgebra_glm::{atan2, dot, mat4_to_mat3, Mat3, Vec2, Vec3, Vec4};
+use nalgebra_glm::{mat4_to_mat3, Mat3, Vec2, Vec3, Vec4};
 use crate::vertex::Vertex;
-use crate::{framebuffer, Uniforms};
-use crate::fragment::Fragment;
-use crate::color::Color;
+use crate::Uniforms;
+use crate::fragment::{Fragment, FragmentOutput};
+use crate::color::{Color, HdrColor};
 use std::f32::consts::PI;
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
@@ -13,19 +13,7 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     vertex.position.z,
     1.0
   );
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-
-  // Perform perspective division
-  let w = transformed.w;
-  let ndc_position = Vec4::new(
-    transformed.x / w,
-    transformed.y / w,
-    transformed.z / w,
-    1.0
-  );
-
-  // apply viewport matrix
-  let screen_position = uniforms.viewport_matrix * ndc_position;
+  let clip_position = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
 
   // Transform normal
   let model_mat3 = mat4_to_mat3(&uniforms.model_matrix); 
@@ -33,34 +21,55 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
 
   let transformed_normal = normal_matrix * vertex.normal;
 
-  // Create a new Vertex with transformed attributes
+  // Create a new Vertex with transformed attributes. The perspective division is
+  // deferred to `screen_mapping` so the clipper can work in homogeneous space.
   Vertex {
     position: vertex.position,
     normal: vertex.normal,
     tex_coords: vertex.tex_coords,
     color: vertex.color,
-    transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
+    clip_position,
+    inv_w: 1.0 / clip_position.w,
+    transformed_position: Vec3::new(clip_position.x, clip_position.y, clip_position.z),
     transformed_normal,
   }
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, id:f32) -> Color {
+pub fn screen_mapping(vertex: &mut Vertex, uniforms: &Uniforms) {
+  // Perform perspective division
+  let w = vertex.clip_position.w;
+  vertex.inv_w = 1.0 / w;
+  let ndc_position = Vec4::new(
+    vertex.clip_position.x / w,
+    vertex.clip_position.y / w,
+    vertex.clip_position.z / w,
+    1.0
+  );
+
+  // apply viewport matrix
+  let screen_position = uniforms.viewport_matrix * ndc_position;
+  vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+}
+
+// Los shaders devuelven luz lineal en HDR; los que no pasan de [0, 1] se convierten desde Color.
+// Los cuerpos que brillan por sí mismos marcan su emisión para el bloom
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, id:f32) -> FragmentOutput {
   match id {
-    1.0 => planet1(fragment, uniforms),
-    2.0 => moon_shader(fragment, uniforms),
+    1.0 => planet1(fragment, uniforms).into(),
+    2.0 => moon_shader(fragment, uniforms).into(),
 
     3.0 => sun_shader(fragment, uniforms),
 
-    4.0 => earth_shader(fragment, uniforms),
-    5.0 => vibrant_blue_planet_shader(fragment, uniforms),
+    4.0 => earth_shader(fragment, uniforms).into(),
+    5.0 => vibrant_blue_planet_shader(fragment, uniforms).into(),
     
-    6.0 => rocky_planet_shader(fragment, uniforms),
-    7.0 => ring_shader(fragment, uniforms),
-    8.0 => planet2(fragment, uniforms),
+    6.0 => rocky_planet_shader(fragment, uniforms).into(),
+    7.0 => ring_shader(fragment, uniforms).into(),
+    8.0 => planet2(fragment, uniforms).into(),
     // Agregar más shaders aquí según sea necesario
     _ => {
         // Por defecto, renderiza un shader genérico
-        Color::new(69, 146, 173 ) // Negro como fallback
+        Color::new(69, 146, 173 ).into() // Negro como fallback
     }
 }
     
@@ -97,7 +106,7 @@ fn planet2(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 
-fn sun_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+fn sun_shader(fragment: &Fragment, _uniforms: &Uniforms) -> FragmentOutput {
 
 
   // Coordenadas del fragmento normalizadas al rango [-1, 1]
@@ -112,7 +121,7 @@ fn sun_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
   let color4 = Color::new(204, 102, 0);   // Naranja oscuro
 
   let t = radius.clamp(0.0, 1.0); // Radio normalizado entre 0 y 1
-  let blended_color = if t < 0.53 {
+  let gradient = if t < 0.53 {
       // Mezcla entre el color1 y color2
       color1.lerp(&color2, t / 0.33)
   } else if t < 0.66 {
@@ -123,7 +132,11 @@ fn sun_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
       color3.lerp(&color4, (t - 0.66) / 0.34)
   };
 
-  blended_color
+  // La superficie se queda en el degradado, que cabe en la gama aun con clamp.
+  // El núcleo emite hasta 4 veces más luz que el blanco, y esa luz solo la
+  // reparte el bloom.
+  let emission = 1.0 + 3.0 * (1.0 - t).powi(2);
+  FragmentOutput::emissive(HdrColor::from(gradient), emission)
 }
 
 
@@ -178,16 +191,14 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       base_color
   };
 
-  let final_color = if is_in_atmosphere {
+  if is_in_atmosphere {
       planet_color * (1.0 - cloud_intensity) + cloud_color
   } else {
       planet_color
-  };
-
-  final_color
+  }
 }
 
-pub fn vibrant_blue_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn vibrant_blue_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> HdrColor {
   let zoom = 3.0;
     let x = fragment.vertex_position.x;
     let y = fragment.vertex_position.y;
@@ -210,10 +221,10 @@ pub fn vibrant_blue_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> C
     let ambient_color = Color::new(200, 80, 198);
 
     // Mezcla del color base y el color ambiental para dar una apariencia gaseosa en toda la superficie
-    base_color * fragment.intensity + ambient_color * ambient_intensity
+    HdrColor::from(base_color) * fragment.intensity + HdrColor::from(ambient_color) * ambient_intensity
 }
 
-fn rocky_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+fn rocky_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> HdrColor {
   let scale = 10.0; // Escala del patrón de ruido
   let light_adjust = 0.6; // Ajuste de intensidad lumínica ambiental
 
@@ -223,7 +234,7 @@ fn rocky_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
   let z = fragment.vertex_position.z;
 
   // Introducir pseudoaleatoriedad en la textura
-  let randomness = (x * 12.9898 + y * 78.233 + z * 37.719).sin() * 43758.5453;
+  let randomness = (x * 12.9898 + y * 78.233 + z * 37.719).sin() * 43_758.547;
   let random_factor = randomness.fract(); // Tomamos solo la parte decimal
 
   // Coordenadas ajustadas con ruido aleatorio
@@ -245,13 +256,11 @@ fn rocky_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
   let ambient_color = Color::new(50, 30, 20); // Luz cálida difusa
 
   // Ajuste de la intensidad de luz
-  let final_color = surface_color * fragment.intensity * light_adjust
-      + ambient_color * ambient_intensity;
-
-  final_color
+  HdrColor::from(surface_color) * (fragment.intensity * light_adjust)
+      + HdrColor::from(ambient_color) * ambient_intensity
 }
 
-fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+fn moon_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
   // Colores base para la superficie lunar
   let color1 = Color::new( 197, 199, 200);
   let color2 = Color::new( 220, 221, 222);
@@ -273,7 +282,7 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 
-fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+fn ring_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
   // Colores base para la superficie lunar
   let base_color = Color::new(184, 162, 42 ); // Gris claro para la superficie
   let crater_color = Color::new(184, 177, 42 ); // Gris oscuro para los cráteres
@@ -309,7 +318,5 @@ fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let shadow_color = Color::new(50, 50, 50); // Sombra suave
 
   // Aplicar iluminación
-  let final_color = surface_color * light_intensity + shadow_color * (1.0 - light_intensity);
-
-  final_color
+  surface_color * light_intensity + shadow_color * (1.0 - light_intensity)
 }
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::mesh::Mesh;
+use crate::vertex::Vertex;
+
+// How the built-in spheres are tessellated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SphereKind {
+    // Stacks and slices of latitude and longitude: seamless texture coordinates,
+    // but the triangles crowd together at the poles
+    Uv,
+    // A subdivided icosahedron: triangles of nearly the same size everywhere
+    Ico,
+}
+
+// Point on the unit sphere for texture coordinates (u, v), with u going once
+// around the Y axis and v from the north pole (0) to the south pole (1)
+fn direction(u: f32, v: f32) -> Vec3 {
+    let (theta, phi) = (v * PI, u * 2.0 * PI);
+    Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+}
+
+// Inverse of `direction`, with u in [0, 1)
+fn tex_coords(direction: &Vec3) -> Vec2 {
+    let u = (-direction.z).atan2(direction.x) / (2.0 * PI);
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    Vec2::new(u.rem_euclid(1.0), v)
+}
+
+fn sphere_vertex(direction: Vec3, tex_coords: Vec2, radius: f32) -> Vertex {
+    Vertex::new(direction * radius, direction, tex_coords)
+}
+
+// Sphere centered on the origin, with triangles counter-clockwise seen from
+// outside like the OBJ models. The first and last longitude share positions but
+// not texture coordinates, and the rows next to the poles are single triangles.
+pub fn uv_sphere(radius: f32, stacks: usize, slices: usize) -> Mesh {
+    let (stacks, slices) = (stacks.max(2), slices.max(3));
+    let at = |stack: usize, slice: usize| {
+        let uv = Vec2::new(slice as f32 / slices as f32, stack as f32 / stacks as f32);
+        sphere_vertex(direction(uv.x, uv.y), uv, radius)
+    };
+
+    let mut triangles = Vec::with_capacity((stacks - 1) * slices * 6);
+    for stack in 0..stacks {
+        for slice in 0..slices {
+            let (top_left, top_right) = (at(stack, slice), at(stack, slice + 1));
+            let (bottom_left, bottom_right) = (at(stack + 1, slice), at(stack + 1, slice + 1));
+            if stack != 0 {
+                triangles.extend([top_left.clone(), bottom_left.clone(), top_right.clone()]);
+            }
+            if stack != stacks - 1 {
+                triangles.extend([top_right, bottom_left, bottom_right]);
+            }
+        }
+    }
+    Mesh::welded(&triangles)
+}
+
+// Icosahedron whose faces are split in four `subdivisions` times, with every
+// new point pushed out to the sphere
+pub fn icosphere(radius: f32, subdivisions: usize) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| Vec3::new(x, y, z).normalize())
+    .collect();
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Edges are shared by two faces, which must agree on the midpoint
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push((points[a] + points[b]).normalize());
+                points.len() - 1
+            })
+        };
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let triangles: Vec<Vertex> = faces
+        .iter()
+        .flat_map(|face| {
+            let directions = face.map(|index| points[index]);
+            let mut uvs = directions.map(|direction| tex_coords(&direction));
+
+            // A face across the seam would interpolate u through the whole texture,
+            // so its points on the u = 0 side move past u = 1 instead
+            let (min_u, max_u) = uvs.iter().fold((1.0_f32, 0.0_f32), |(lo, hi), uv| (lo.min(uv.x), hi.max(uv.x)));
+            if max_u - min_u > 0.5 {
+                uvs.iter_mut().filter(|uv| uv.x < 0.5).for_each(|uv| uv.x += 1.0);
+            }
+
+            // u is undefined at the poles: take the middle of the other two points
+            for pole in 0..3 {
+                if directions[pole].y.abs() > 1.0 - 1e-6 {
+                    uvs[pole].x = (uvs[(pole + 1) % 3].x + uvs[(pole + 2) % 3].x) / 2.0;
+                }
+            }
+
+            (0..3).map(move |i| sphere_vertex(directions[i], uvs[i], radius))
+        })
+        .collect();
+    Mesh::welded(&triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every triangle faces away from the center, with unit normals on the surface
+    fn assert_outward(mesh: &Mesh, radius: f32) {
+        for tri in soup(mesh).chunks_exact(3) {
+            let normal = (tri[1].position - tri[0].position).cross(&(tri[2].position - tri[0].position));
+            assert!(normal.dot(&tri[0].position) > 0.0);
+            for vertex in tri {
+                assert!((vertex.position.magnitude() - radius).abs() < 1e-5);
+                assert!((vertex.normal - vertex.position / radius).magnitude() < 1e-5);
+                assert!((0.0..=1.0).contains(&vertex.tex_coords.y));
+            }
+        }
+    }
+
+    fn soup(mesh: &Mesh) -> Vec<Vertex> {
+        mesh.indices.iter().map(|&index| mesh.vertices[index as usize].clone()).collect()
+    }
+
+    #[test]
+    fn uv_spheres_are_closed_and_face_outwards() {
+        let mesh = uv_sphere(0.5, 8, 16);
+        assert_eq!(mesh.triangle_count(), (8 - 1) * 16 * 2);
+        // A grid of 7 rings of 17 points, the last repeating the first with u = 1, and a
+        // point at each pole for every slice
+        assert_eq!(mesh.vertices.len(), 7 * 17 + 2 * 16);
+        assert_outward(&mesh, 0.5);
+        let vertices = &mesh.vertices;
+        assert!(vertices.iter().all(|vertex| (0.0..=1.0).contains(&vertex.tex_coords.x)));
+        assert!(vertices.iter().all(|vertex| (tex_coords(&vertex.normal).y - vertex.tex_coords.y).abs() < 1e-5));
+    }
+
+    #[test]
+    fn icospheres_quadruple_their_faces_per_subdivision() {
+        for subdivisions in 0..4 {
+            let mesh = icosphere(2.0, subdivisions);
+            assert_eq!(mesh.triangle_count(), 20 * 4_usize.pow(subdivisions as u32));
+            assert_outward(&mesh, 2.0);
+            let vertices = soup(&mesh);
+            // Past the bare icosahedron no face spans more than a fraction of the
+            // texture, even across the seam
+            for tri in vertices.chunks_exact(3).filter(|_| subdivisions > 0) {
+                let us = tri.iter().map(|vertex| vertex.tex_coords.x);
+                let (lo, hi) = us.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), u| (lo.min(u), hi.max(u)));
+                assert!(hi - lo < 0.25);
+            }
+        }
+    }
+}
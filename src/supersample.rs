@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DownsampleFilter {
+    Box,
+    Tent,
+    Lanczos,
+}
+
+impl DownsampleFilter {
+    // Half-width of the kernel, in output pixels
+    fn support(self) -> f32 {
+        match self {
+            DownsampleFilter::Box => 0.5,
+            DownsampleFilter::Tent => 1.0,
+            DownsampleFilter::Lanczos => 3.0,
+        }
+    }
+
+    // Kernel weight at a distance `x` from the center, in output pixels
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            DownsampleFilter::Box => if x <= 0.5 { 1.0 } else { 0.0 },
+            DownsampleFilter::Tent => (1.0 - x).max(0.0),
+            DownsampleFilter::Lanczos => if x < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Renders at `factor` times the output size and filters the result down to
+// `width` x `height`. `draw` receives the enlarged framebuffer, already cleared,
+// and must build its viewport from the framebuffer's own size. It is also in
+// charge of resolving and presenting, so `buffer` holds the final image when it
+// returns. No window is involved.
+pub fn render_supersampled<F: FnOnce(&mut Framebuffer)>(
+    width: usize,
+    height: usize,
+    factor: usize,
+    filter: DownsampleFilter,
+    draw: F,
+) -> Vec<u32> {
+    let mut framebuffer = Framebuffer::new(width * factor, height * factor);
+    framebuffer.clear();
+    draw(&mut framebuffer);
+
+    downsample(&framebuffer.buffer, width * factor, height * factor, factor, filter)
+}
+
+// Shrinks an image by an integer factor with a separable filter
+pub fn downsample(
+    source: &[u32],
+    source_width: usize,
+    source_height: usize,
+    factor: usize,
+    filter: DownsampleFilter,
+) -> Vec<u32> {
+    let width = source_width / factor;
+    let height = source_height / factor;
+
+    let channels: Vec<[f32; 3]> = source
+        .iter()
+        .map(|&c| [((c >> 16) & 0xFF) as f32, ((c >> 8) & 0xFF) as f32, (c & 0xFF) as f32])
+        .collect();
+
+    // Horizontal pass: source_width x source_height -> width x source_height
+    let columns = filter_weights(width, source_width, factor, filter);
+    let mut horizontal = vec![[0.0; 3]; width * source_height];
+    for y in 0..source_height {
+        let row = &channels[y * source_width..(y + 1) * source_width];
+        for (x, taps) in columns.iter().enumerate() {
+            horizontal[y * width + x] = accumulate(taps.iter().map(|&(i, w)| (&row[i], w)));
+        }
+    }
+
+    // Vertical pass: width x source_height -> width x height
+    let rows = filter_weights(height, source_height, factor, filter);
+    let mut output = vec![0; width * height];
+    for (y, taps) in rows.iter().enumerate() {
+        for x in 0..width {
+            let [r, g, b] = accumulate(taps.iter().map(|&(i, w)| (&horizontal[i * width + x], w)));
+            let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u32;
+            output[y * width + x] = (to_u8(r) << 16) | (to_u8(g) << 8) | to_u8(b);
+        }
+    }
+
+    output
+}
+
+fn accumulate<'a>(taps: impl Iterator<Item = (&'a [f32; 3], f32)>) -> [f32; 3] {
+    taps.fold([0.0; 3], |acc, (color, w)| {
+        [acc[0] + color[0] * w, acc[1] + color[1] * w, acc[2] + color[2] * w]
+    })
+}
+
+// For every output pixel, the source pixels it reads and their normalized weights
+fn filter_weights(output_len: usize, source_len: usize, factor: usize, filter: DownsampleFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = factor as f32;
+    let radius = (filter.support() * scale).ceil() as isize;
+
+    (0..output_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let first = center.floor() as isize - radius;
+            let last = center.floor() as isize + radius;
+
+            let mut taps: Vec<(usize, f32)> = (first..=last)
+                .map(|j| {
+                    let w = filter.weight((j as f32 + 0.5 - center) / scale);
+                    // Pixels past the border repeat the edge
+                    (j.clamp(0, source_len as isize - 1) as usize, w)
+                })
+                .filter(|&(_, w)| w != 0.0)
+                .collect();
+
+            let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+            for tap in taps.iter_mut() {
+                tap.1 /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_image_stays_flat_with_every_filter() {
+        let source = vec![0x336699; 32 * 24];
+        for filter in [DownsampleFilter::Box, DownsampleFilter::Tent, DownsampleFilter::Lanczos] {
+            let output = downsample(&source, 32, 24, 4, filter);
+            assert_eq!(output.len(), 8 * 6);
+            assert!(output.iter().all(|&c| c == 0x336699), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_each_block() {
+        // 2x2 blocks with two white and two black pixels become mid gray
+        let source: Vec<u32> = (0..16).map(|i| if (i % 4 + i / 4) % 2 == 0 { 0xFFFFFF } else { 0 }).collect();
+        let output = downsample(&source, 4, 4, 2, DownsampleFilter::Box);
+        assert_eq!(output, vec![0x808080; 4]);
+    }
+}
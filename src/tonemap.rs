@@ -0,0 +1,77 @@
+use crate::color::{Color, HdrColor};
+
+// Operators that compress linear HDR light into the displayable [0, 1] range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    // Plain clamp, bright values saturate to white
+    Clamp,
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::Clamp => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Clamp,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Reinhard => "Reinhard",
+            ToneMapping::Aces => "ACES",
+        }
+    }
+
+    fn curve(self, x: f32) -> f32 {
+        match self {
+            ToneMapping::Clamp => x,
+            ToneMapping::Reinhard => x / (1.0 + x),
+            ToneMapping::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        }
+    }
+
+    // Scales by `exposure`, applies the curve and encodes the result back to
+    // sRGB. The result is display-referred, every channel in [0, 1].
+    pub fn map(self, color: HdrColor, exposure: f32) -> HdrColor {
+        let encode = |c: f32| self.curve((c * exposure).max(0.0)).clamp(0.0, 1.0).powf(1.0 / 2.2);
+        HdrColor::new(encode(color.r), encode(color.g), encode(color.b))
+    }
+}
+
+// Rounds a display-referred color to 8 bits per channel
+pub fn quantize(color: HdrColor) -> Color {
+    let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Color::new(to_u8(color.r), to_u8(color.g), to_u8(color.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `map` rounded to 8 bits per channel
+    fn apply(tone_mapping: ToneMapping, color: HdrColor, exposure: f32) -> Color {
+        quantize(tone_mapping.map(color, exposure))
+    }
+
+    #[test]
+    fn clamp_round_trips_ldr_colors() {
+        let color = Color::new(200, 100, 30);
+        assert_eq!(apply(ToneMapping::Clamp, HdrColor::from(color), 1.0).to_hex(), color.to_hex());
+    }
+
+    #[test]
+    fn bright_light_keeps_its_hue() {
+        // An orange four times brighter than white must not turn white
+        let light = HdrColor::from(Color::new(255, 128, 0)) * 4.0;
+        for tone_mapping in [ToneMapping::Reinhard, ToneMapping::Aces] {
+            let mapped = apply(tone_mapping, light, 1.0).to_hex();
+            let (r, g, b) = (mapped >> 16, (mapped >> 8) & 0xFF, mapped & 0xFF);
+            assert!(r > g && g > b, "{:?}: {:06X}", tone_mapping, mapped);
+        }
+    }
+}
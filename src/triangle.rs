@@ -2,68 +2,275 @@ use nalgebra_glm::{Vec3, dot, Vec2};
 use crate::fragment::Fragment;
 use crate::vertex::{Vertex};
 use crate::color::Color;
+use crate::framebuffer::MAX_SAMPLES;
+use crate::line::{rasterize_line, vertex_fragment};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+// Decides from the screen-space winding whether a triangle should be discarded.
+// The viewport flips the y axis, so a positive edge function here means the
+// triangle was counter-clockwise in normalized device coordinates.
+pub fn is_culled(v1: &Vertex, v2: &Vertex, v3: &Vertex, cull_mode: CullMode, front_face: FrontFace) -> bool {
+    if cull_mode == CullMode::None {
+        return false;
+    }
+
+    let area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+    let is_front = match front_face {
+        FrontFace::CounterClockwise => area > 0.0,
+        FrontFace::Clockwise => area < 0.0,
+    };
+
+    match cull_mode {
+        CullMode::Back => !is_front,
+        CullMode::Front => is_front,
+        CullMode::None => false,
+    }
+}
+
+// How triangles are drawn: filled, as their edges, as their vertices, or filled
+// with the edges on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Solid,
+    Wireframe,
+    Points,
+    SolidWireframe,
+}
+
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Solid => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Points,
+            RenderMode::Points => RenderMode::SolidWireframe,
+            RenderMode::SolidWireframe => RenderMode::Solid,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Solid => "solid",
+            RenderMode::Wireframe => "wireframe",
+            RenderMode::Points => "points",
+            RenderMode::SolidWireframe => "solid + wireframe",
+        }
+    }
+}
+
+// Side in pixels of the square drawn for every vertex in `RenderMode::Points`
+pub const POINT_SIZE: i32 = 3;
+
+// Edges and vertices are pulled towards the camera by this fraction of their
+// distance, so they win the depth test against the faces they outline
+const OVERLAY_DEPTH_BIAS: f32 = 0.005;
+
+// Vertex positions are snapped to a fixed-point grid with this many bits of
+// sub-pixel precision, so the coverage test is exact
+const SUBPIXEL_BITS: i64 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+
+// Rasterizes a triangle, handing each covered fragment to `emit` as soon as it is
+// produced instead of collecting them. Only pixels inside `scissor`
+// (min_x, min_y, max_x, max_y, max exclusive) are visited. Coverage and depth are
+// computed at every position of `sample_positions`, attributes once per pixel.
+pub fn triangle<F: FnMut(Fragment)>(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    scissor: (i32, i32, i32, i32),
+    sample_positions: &[(f32, f32)],
+    emit: &mut F,
+) {
+    // Snap to the sub-pixel grid and make the winding positive, so the same
+    // inside test works for both orientations
+    let mut area = edge_function_fixed(
+        snap(&v1.transformed_position),
+        snap(&v2.transformed_position),
+        snap(&v3.transformed_position),
+    );
+    if area == 0 {
+        return;
+    }
+    let (v2, v3) = if area < 0 { (v3, v2) } else { (v2, v3) };
+    area = area.abs();
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let (fa, fb, fc) = (snap(&a), snap(&b), snap(&c));
     let (t1, t2, t3) = (v1.tex_coords, v2.tex_coords, v3.tex_coords);
 
+    // Top-left fill rule: a pixel center lying exactly on an edge only belongs to
+    // the triangle if that edge is a top or left edge
+    let bias1 = if is_top_left(fb, fc) { 0 } else { -1 };
+    let bias2 = if is_top_left(fc, fa) { 0 } else { -1 };
+    let bias3 = if is_top_left(fa, fb) { 0 } else { -1 };
+
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    let (min_x, min_y) = (min_x.max(scissor.0), min_y.max(scissor.1));
+    let (max_x, max_y) = (max_x.min(scissor.2 - 1), max_y.min(scissor.3 - 1));
 
     let light_dir = Vec3::new(0.0, 0.0, 1.0);
 
-    let triangle_area = edge_function(&a, &b, &c);
-
     // Iterate over each pixel in the bounding box
     for y in min_y..=max_y {
         for x in min_x..=max_x {
-            let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+            let edges_at = |point: (i64, i64)| {
+                (
+                    edge_function_fixed(fb, fc, point),
+                    edge_function_fixed(fc, fa, point),
+                    edge_function_fixed(fa, fb, point),
+                )
+            };
+            let inside = |(e1, e2, e3): (i64, i64, i64)| e1 + bias1 >= 0 && e2 + bias2 >= 0 && e3 + bias3 >= 0;
+            let depth_at = |(e1, e2, e3): (i64, i64, i64)| {
+                (a.z * e1 as f32 + b.z * e2 as f32 + c.z * e3 as f32) / area as f32
+            };
+
+            // Coverage and depth of each sample, in exact integer arithmetic
+            let mut coverage = 0u8;
+            let mut sample_depths = [0.0; MAX_SAMPLES];
+            let mut first_covered = None;
+            for (s, &(sx, sy)) in sample_positions.iter().enumerate() {
+                let edges = edges_at((
+                    x as i64 * SUBPIXEL_ONE + (sx * SUBPIXEL_ONE as f32) as i64,
+                    y as i64 * SUBPIXEL_ONE + (sy * SUBPIXEL_ONE as f32) as i64,
+                ));
+                if inside(edges) {
+                    coverage |= 1 << s;
+                    sample_depths[s] = depth_at(edges);
+                    first_covered.get_or_insert(edges);
+                }
+            }
+
+            // Check if the pixel is touched by the triangle at all
+            let Some(first_covered) = first_covered else {
+                continue;
+            };
+
+            // Attributes are evaluated at the pixel center, or at a covered sample
+            // when the center falls outside the triangle
+            let center = edges_at((
+                x as i64 * SUBPIXEL_ONE + SUBPIXEL_ONE / 2,
+                y as i64 * SUBPIXEL_ONE + SUBPIXEL_ONE / 2,
+            ));
+            let (e1, e2, e3) = if inside(center) { center } else { first_covered };
 
             // Calculate barycentric coordinates
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+            let (w1, w2, w3) = (
+                e1 as f32 / area as f32,
+                e2 as f32 / area as f32,
+                e3 as f32 / area as f32,
+            );
 
-            // Check if the point is inside the triangle
-            if w1 >= 0.0 && w1 <= 1.0 && 
-            w2 >= 0.0 && w2 <= 1.0 &&
-            w3 >= 0.0 && w3 <= 1.0 {
+            // Interpolate depth (z/w is linear in screen space)
+            let depth = depth_at((e1, e2, e3));
 
-                // Interpolate normal
-                let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-                let normal = normal.normalize();
+            // Perspective-correct weights for the remaining attributes
+            let (w1, w2, w3) = perspective_correct(w1, w2, w3, v1.inv_w, v2.inv_w, v3.inv_w);
 
-                // Calculate lighting intensity
-                let intensity = dot(&normal, &light_dir).max(0.0);
+            // Interpolate normal
+            let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+            let normal = normal.normalize();
 
-                // Create a gray color (unchanged)
-                let color = Color::new(100, 100, 100); // Medium gray
+            // Calculate lighting intensity
+            let intensity = dot(&normal, &light_dir).max(0.0);
 
-                // Interpolate depth
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+            // Create a gray color (unchanged)
+            let color = Color::new(100, 100, 100); // Medium gray
 
-                // Positions of the original vertex
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+            // Positions of the original vertex
+            let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
 
-                // Interpolate texture coordinates
-                let tex_u = t1.x * w1 + t2.x * w2 + t3.x * w3;
-                let tex_v = t1.y * w1 + t2.y * w2 + t3.y * w3;
+            // Interpolate texture coordinates
+            let tex_u = t1.x * w1 + t2.x * w2 + t3.x * w3;
+            let tex_v = t1.y * w1 + t2.y * w2 + t3.y * w3;
 
-                fragments.push(Fragment::new(
-                    Vec2::new(x as f32, y as f32),
-                    color,
-                    depth,
-                    normal,
-                    intensity,
-                    vertex_position,
-                    Vec2::new(tex_u, tex_v),
-                ));
+            let mut fragment = Fragment::new(
+                Vec2::new(x as f32, y as f32),
+                color,
+                depth,
+                normal,
+                intensity,
+                vertex_position,
+                Vec2::new(tex_u, tex_v),
+            );
+            fragment.coverage = coverage;
+            fragment.sample_depths = sample_depths;
+            emit(fragment);
+        }
+    }
+}
+
+// Alternative to `triangle` for `RenderMode::Wireframe`: draws the edges v1-v2,
+// v2-v3 and v3-v1 whose flag in `edges` is set as depth-tested lines covering
+// every sample. Edges added by clipping are left out.
+pub fn wireframe<F: FnMut(Fragment)>(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    edges: [bool; 3],
+    scissor: (i32, i32, i32, i32),
+    sample_positions: &[(f32, f32)],
+    emit: &mut F,
+) {
+    let coverage = full_coverage(sample_positions);
+    let mut emit_biased = |fragment: Fragment| emit(with_overlay_bias(fragment));
+    for ((a, b), _) in [(v1, v2), (v2, v3), (v3, v1)].into_iter().zip(edges).filter(|&(_, drawn)| drawn) {
+        rasterize_line(a, b, scissor, coverage, &mut emit_biased);
+    }
+}
+
+// Alternative to `triangle` for `RenderMode::Points`: draws a `POINT_SIZE` square
+// around each vertex whose flag in `corners` is set, so that vertices added by
+// clipping or shared with other triangles can be left out
+pub fn points<F: FnMut(Fragment)>(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    corners: [bool; 3],
+    scissor: (i32, i32, i32, i32),
+    sample_positions: &[(f32, f32)],
+    emit: &mut F,
+) {
+    let coverage = full_coverage(sample_positions);
+    for (vertex, _) in [v1, v2, v3].into_iter().zip(corners).filter(|&(_, drawn)| drawn) {
+        let (cx, cy) = (vertex.transformed_position.x.floor() as i32, vertex.transformed_position.y.floor() as i32);
+        let (min_x, min_y) = ((cx - POINT_SIZE / 2).max(scissor.0), (cy - POINT_SIZE / 2).max(scissor.1));
+        let (max_x, max_y) = ((cx + POINT_SIZE / 2).min(scissor.2 - 1), (cy + POINT_SIZE / 2).min(scissor.3 - 1));
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                emit(with_overlay_bias(vertex_fragment(vertex, x, y, coverage)));
             }
         }
     }
+}
+
+fn full_coverage(sample_positions: &[(f32, f32)]) -> u8 {
+    ((1u16 << sample_positions.len()) - 1) as u8
+}
 
-    fragments
+// Depth is z/w in [-1, 1], so 1 - depth is roughly proportional to 1/distance
+// and this shift is a constant fraction of the distance
+fn with_overlay_bias(mut fragment: Fragment) -> Fragment {
+    for depth in fragment.sample_depths.iter_mut() {
+        *depth -= OVERLAY_DEPTH_BIAS * (1.0 - *depth);
+    }
+    fragment.depth -= OVERLAY_DEPTH_BIAS * (1.0 - fragment.depth);
+    fragment
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
     let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
@@ -72,16 +279,287 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
-    let w1 = edge_function(b, c, p) / area;
-    let w2 = edge_function(c, a, p) / area;
-    let w3 = edge_function(a, b, p) / area;
+fn snap(v: &Vec3) -> (i64, i64) {
+    (
+        (v.x * SUBPIXEL_ONE as f32).round() as i64,
+        (v.y * SUBPIXEL_ONE as f32).round() as i64,
+    )
+}
+
+// Expects the edge to belong to a triangle with positive area, whose interior is
+// where the edge function is positive
+fn is_top_left(a: (i64, i64), b: (i64, i64)) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    // left edge: interior to its right; top edge: horizontal with interior below
+    dy > 0 || (dy == 0 && dx < 0)
+}
+
+// Turns screen-space barycentrics into weights that interpolate attributes linearly
+// in eye space, by interpolating attribute/w and 1/w and dividing
+fn perspective_correct(w1: f32, w2: f32, w3: f32, inv_w1: f32, inv_w2: f32, inv_w3: f32) -> (f32, f32, f32) {
+    let (p1, p2, p3) = (w1 * inv_w1, w2 * inv_w2, w3 * inv_w3);
+    let sum = p1 + p2 + p3;
 
-    (w1, w2, w3)
+    (p1 / sum, p2 / sum, p3 / sum)
 }
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
 
+fn edge_function_fixed(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> i64 {
+    (c.0 - a.0) * (b.1 - a.1) - (c.1 - a.1) * (b.0 - a.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{perspective, Mat4, Vec4};
+    use crate::shaders::screen_mapping;
+    use crate::{create_viewport_matrix, Uniforms};
+
+    const WIDTH: f32 = 200.0;
+    const HEIGHT: f32 = 200.0;
 
+    fn project(position: Vec3, tex_coords: Vec2, uniforms: &Uniforms) -> Vertex {
+        let mut vertex = Vertex::new(position, Vec3::new(0.0, 0.0, 1.0), tex_coords);
+        vertex.clip_position = uniforms.projection_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+        screen_mapping(&mut vertex, uniforms);
+        vertex
+    }
+
+    #[test]
+    fn textured_quad_matches_analytic_uvs() {
+        let uniforms = Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: Mat4::identity(),
+            projection_matrix: perspective(WIDTH / HEIGHT, 1.2, 0.1, 100.0),
+            viewport_matrix: create_viewport_matrix(WIDTH, HEIGHT),
+            time: 0,
+        };
+
+        // A quad receding from the camera: the plane z = -4 - 2y, with u = (x + 1) / 2
+        // and v = (y + 1) / 2 over x, y in [-1, 1]
+        let corners = [
+            project(Vec3::new(-1.0, -1.0, -2.0), Vec2::new(0.0, 0.0), &uniforms),
+            project(Vec3::new(1.0, -1.0, -2.0), Vec2::new(1.0, 0.0), &uniforms),
+            project(Vec3::new(1.0, 1.0, -6.0), Vec2::new(1.0, 1.0), &uniforms),
+            project(Vec3::new(-1.0, 1.0, -6.0), Vec2::new(0.0, 1.0), &uniforms),
+        ];
+
+        let mut fragments = Vec::new();
+        let scissor = (0, 0, WIDTH as i32, HEIGHT as i32);
+        triangle(&corners[0], &corners[1], &corners[2], scissor, &[(0.5, 0.5)], &mut |f: Fragment| fragments.push(f));
+        triangle(&corners[0], &corners[2], &corners[3], scissor, &[(0.5, 0.5)], &mut |f: Fragment| fragments.push(f));
+        assert!(fragments.len() > 1000);
+
+        let p00 = uniforms.projection_matrix[(0, 0)];
+        let p11 = uniforms.projection_matrix[(1, 1)];
+        let mut max_error: f32 = 0.0;
+        for fragment in &fragments {
+            // Cast a ray through the pixel center and intersect it with the quad's plane
+            let ndc_x = (fragment.position.x + 0.5) / (WIDTH / 2.0) - 1.0;
+            let ndc_y = 1.0 - (fragment.position.y + 0.5) / (HEIGHT / 2.0);
+            let direction = Vec3::new(ndc_x / p00, ndc_y / p11, -1.0);
+            let t = 4.0 / (1.0 - 2.0 * direction.y);
+            let expected = Vec2::new((t * direction.x + 1.0) / 2.0, (t * direction.y + 1.0) / 2.0);
+
+            max_error = max_error.max((fragment.tex_coords - expected).abs().max());
+        }
+
+        assert!(max_error < 1e-3, "max uv error {}", max_error);
+    }
+
+    // Rasterizes screen-space triangles and counts how many times each pixel is covered
+    fn coverage(triangles: &[[Vec2; 3]], width: usize, height: usize) -> Vec<u32> {
+        let mut counts = vec![0; width * height];
+        let scissor = (0, 0, width as i32, height as i32);
+        for tri in triangles {
+            let [a, b, c] = tri.map(|p| Vertex::new(Vec3::new(p.x, p.y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros()));
+            triangle(&a, &b, &c, scissor, &[(0.5, 0.5)], &mut |f: Fragment| {
+                counts[f.position.y as usize * width + f.position.x as usize] += 1;
+            });
+        }
+        counts
+    }
+
+    // Splits a grid of vertices into triangles, alternating diagonals and windings
+    fn tessellate(grid: &[Vec<Vec2>]) -> Vec<[Vec2; 3]> {
+        let mut triangles = Vec::new();
+        for row in 0..grid.len() - 1 {
+            for col in 0..grid[row].len() - 1 {
+                let (p00, p10) = (grid[row][col], grid[row][col + 1]);
+                let (p01, p11) = (grid[row + 1][col], grid[row + 1][col + 1]);
+                if (row + col) % 2 == 0 {
+                    triangles.push([p00, p10, p11]);
+                    triangles.push([p00, p01, p11]);
+                } else {
+                    triangles.push([p10, p00, p01]);
+                    triangles.push([p10, p11, p01]);
+                }
+            }
+        }
+        triangles
+    }
+
+    #[test]
+    fn jittered_mesh_is_covered_exactly_once() {
+        let (cols, rows, cell) = (12, 9, 10.0);
+        let origin = Vec2::new(10.0, 10.0);
+
+        // Interior vertices are displaced by a deterministic pseudo-random sub-pixel
+        // amount; the outline stays on pixel boundaries
+        let mut seed: u32 = 12345;
+        let mut jitter = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((seed >> 8) % 1000) as f32 / 1000.0 * 7.4 - 3.7
+        };
+        let grid: Vec<Vec<Vec2>> = (0..=rows)
+            .map(|row| {
+                (0..=cols)
+                    .map(|col| {
+                        let mut p = origin + Vec2::new(col as f32, row as f32) * cell;
+                        if col > 0 && col < cols && row > 0 && row < rows {
+                            p += Vec2::new(jitter(), jitter());
+                        }
+                        p
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let (width, height) = (160, 120);
+        let counts = coverage(&tessellate(&grid), width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let inside = (10..130).contains(&x) && (10..100).contains(&y);
+                assert_eq!(counts[y * width + x], inside as u32, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn edges_through_pixel_centers_are_covered_exactly_once() {
+        // Every vertex sits on a pixel center, so every shared edge crosses pixel centers
+        let grid: Vec<Vec<Vec2>> = (0..=10)
+            .map(|row| (0..=10).map(|col| Vec2::new(col as f32 * 4.0 + 5.5, row as f32 * 3.0 + 5.5)).collect())
+            .collect();
+
+        let (width, height) = (60, 50);
+        let counts = coverage(&tessellate(&grid), width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let count = counts[y * width + x];
+                assert!(count <= 1, "pixel ({}, {}) covered {} times", x, y, count);
+                if (6..45).contains(&x) && (6..35).contains(&y) {
+                    assert_eq!(count, 1, "gap at pixel ({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_fan_is_covered_exactly_once() {
+        let center = Vec2::new(50.3, 49.7);
+        let (segments, radius) = (64, 40.0);
+        let rim: Vec<Vec2> = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                center + Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        let triangles: Vec<[Vec2; 3]> = (0..segments)
+            .map(|i| [center, rim[i], rim[(i + 1) % segments]])
+            .collect();
+
+        let (width, height) = (100, 100);
+        let counts = coverage(&triangles, width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let count = counts[y * width + x];
+                assert!(count <= 1, "pixel ({}, {}) covered {} times", x, y, count);
+                let distance = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center).norm();
+                if distance < radius - 1.0 {
+                    assert_eq!(count, 1, "gap at pixel ({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shared_edge_samples_are_covered_exactly_once() {
+        let samples = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+        let corners = [Vec2::new(2.3, 1.7), Vec2::new(27.6, 4.1), Vec2::new(25.2, 22.9), Vec2::new(3.9, 19.4)];
+        let (width, height) = (30, 25);
+        let mut counts = vec![0u32; width * height * samples.len()];
+        for tri in [[corners[0], corners[1], corners[2]], [corners[0], corners[2], corners[3]]] {
+            let [a, b, c] = tri.map(|p| Vertex::new(Vec3::new(p.x, p.y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros()));
+            triangle(&a, &b, &c, (0, 0, width as i32, height as i32), &samples, &mut |f: Fragment| {
+                let first = (f.position.y as usize * width + f.position.x as usize) * samples.len();
+                for (s, count) in counts[first..first + samples.len()].iter_mut().enumerate() {
+                    *count += ((f.coverage >> s) & 1) as u32;
+                }
+            });
+        }
+
+        // Every sample along the diagonal belongs to exactly one of the two triangles
+        assert!(counts.iter().all(|&count| count <= 1));
+        let partially_covered = counts
+            .chunks(samples.len())
+            .filter(|pixel| pixel.contains(&0) && pixel.contains(&1))
+            .count();
+        assert!(partially_covered > 0);
+        for y in 6..18 {
+            for x in 6..24 {
+                let first = (y * width + x) * samples.len();
+                assert!(counts[first..first + samples.len()].iter().all(|&count| count == 1));
+            }
+        }
+    }
+
+    #[test]
+    fn affine_weights_are_unchanged_without_perspective() {
+        let (w1, w2, w3) = perspective_correct(0.2, 0.3, 0.5, 0.25, 0.25, 0.25);
+        assert!((w1 - 0.2).abs() < 1e-6);
+        assert!((w2 - 0.3).abs() < 1e-6);
+        assert!((w3 - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wireframe_outlines_the_filled_triangle() {
+        let corners = [Vec2::new(4.2, 3.6), Vec2::new(35.7, 9.1), Vec2::new(12.4, 27.8)];
+        let [a, b, c] = corners.map(|p| Vertex::new(Vec3::new(p.x, p.y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros()));
+        let (width, height) = (40, 30);
+        let filled = coverage(&[corners], width, height);
+
+        let mut edges = Vec::new();
+        wireframe(&a, &b, &c, [true; 3], (0, 0, width as i32, height as i32), &[(0.5, 0.5)], &mut |f: Fragment| {
+            assert!(f.depth < 0.5, "edges are not pulled towards the camera");
+            edges.push((f.position.x as usize, f.position.y as usize));
+        });
+
+        // Every edge pixel touches the filled area, and every corner is drawn
+        for &(x, y) in &edges {
+            let near_fill = (y.saturating_sub(1)..=(y + 1).min(height - 1))
+                .any(|ny| (x.saturating_sub(1)..=(x + 1).min(width - 1)).any(|nx| filled[ny * width + nx] > 0));
+            assert!(near_fill, "edge pixel ({}, {}) is away from the triangle", x, y);
+        }
+        for corner in corners {
+            assert!(edges.contains(&(corner.x as usize, corner.y as usize)), "corner {:?} is missing", corner);
+        }
+    }
+
+    #[test]
+    fn wireframe_skips_edges_added_by_clipping() {
+        // A right triangle whose hypotenuse is a fan diagonal
+        let [a, b, c] = [Vec2::new(2.5, 2.5), Vec2::new(30.5, 2.5), Vec2::new(2.5, 20.5)]
+            .map(|p| Vertex::new(Vec3::new(p.x, p.y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros()));
+        let mut edges = Vec::new();
+        wireframe(&a, &b, &c, [true, false, true], (0, 0, 40, 30), &[(0.5, 0.5)], &mut |f: Fragment| {
+            edges.push((f.position.x as usize, f.position.y as usize));
+        });
+
+        assert!(edges.iter().all(|&(x, y)| x == 2 || y == 2), "the diagonal was drawn");
+        assert!(edges.contains(&(30, 2)) && edges.contains(&(2, 20)));
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -7,6 +7,9 @@ pub struct Vertex {
   pub normal: Vec3,
   pub tex_coords: Vec2,
   pub color: Color,
+  pub clip_position: Vec4,
+  // 1/w of the clip-space position, used for perspective-correct interpolation
+  pub inv_w: f32,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
 }
@@ -18,6 +21,8 @@ impl Vertex {
       normal,
       tex_coords,
       color: Color::black(),
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      inv_w: 1.0,
       transformed_position: position,
       transformed_normal: normal,
     }
@@ -29,6 +34,8 @@ impl Vertex {
       normal: Vec3::new(0.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color,
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      inv_w: 1.0,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
     }
@@ -38,6 +45,20 @@ impl Vertex {
     self.transformed_position = position;
     self.transformed_normal = normal;
   }
+
+  // Linear interpolation of every attribute, used by the clipper to build new vertices
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      clip_position: self.clip_position.lerp(&other.clip_position, t),
+      inv_w: self.inv_w + (other.inv_w - self.inv_w) * t,
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+    }
+  }
 }
 
 impl Default for Vertex {
@@ -47,6 +68,8 @@ impl Default for Vertex {
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      inv_w: 1.0,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
     }
@@ -0,0 +1,368 @@
+// Golden-image tests: renders canonical scenes without a window and compares
+// them with the reference PNGs in tests/golden. After an intended change of
+// look, regenerate the references with
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden
+//
+// and review the new images before committing them. On failure the rendered
+// image and a diff (mismatched pixels in red over the dimmed reference) are
+// written to target/tmp/golden.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::{Rgb, RgbImage};
+use nalgebra_glm::{Vec2, Vec3};
+use proyecto3::bloom::Bloom;
+use proyecto3::camera::Camera;
+use proyecto3::color::{BlendMode, HdrColor};
+use proyecto3::debug_view::DebugView;
+use proyecto3::export::to_rgb_image;
+use proyecto3::framebuffer::Framebuffer;
+use proyecto3::lod::Lod;
+use proyecto3::mesh::Mesh;
+use proyecto3::model::Obj;
+use proyecto3::post::PostChain;
+use proyecto3::sphere::SphereKind;
+use proyecto3::tonemap::ToneMapping;
+use proyecto3::triangle::{CullMode, FrontFace, RenderMode, POINT_SIZE};
+use proyecto3::vertex::Vertex;
+use proyecto3::{finish_debug_frame, finish_frame, load_scene, render_scene, Instance, InstancedObject, Presentation, Scene, SceneObject};
+
+const SHADER_SIZE: (usize, usize) = (128, 128);
+const SYSTEM_SIZE: (usize, usize) = (240, 180);
+const MSAA_SAMPLES: usize = 4;
+const TIME: u32 = 300;
+// Asteroids of the belt golden
+const ASTEROIDS: usize = 300;
+
+// Largest difference in any channel for two pixels to count as equal
+const TOLERANCE: u8 = 8;
+// Share of pixels allowed past the tolerance, for triangle edges that land on
+// another pixel with a different float rounding
+const MAX_MISMATCHED: f64 = 0.002;
+// Peak signal-to-noise ratio of the whole image, in dB
+const MIN_PSNR: f64 = 40.0;
+
+#[test]
+fn gas_giant_shader() {
+    check_shader("gas_giant", 1.0);
+}
+
+#[test]
+fn moon_shader() {
+    check_shader("moon", 2.0);
+}
+
+#[test]
+fn sun_shader() {
+    check_shader("sun", 3.0);
+}
+
+#[test]
+fn earth_shader() {
+    check_shader("earth", 4.0);
+}
+
+#[test]
+fn vibrant_blue_planet_shader() {
+    check_shader("vibrant_blue_planet", 5.0);
+}
+
+#[test]
+fn rocky_planet_shader() {
+    check_shader("rocky_planet", 6.0);
+}
+
+#[test]
+fn striped_planet_shader() {
+    check_shader("striped_planet", 8.0);
+}
+
+#[test]
+fn ring_shader() {
+    let rings = body("assets/rings.obj", 7.0, 0.4, Vec3::new(0.5, 0.8, 0.0));
+    let image = render(&single(rings), &close_up_camera(), SHADER_SIZE);
+    check_golden("ring", &image);
+}
+
+fn solar_system_scene(sphere: SphereKind, asteroids: usize) -> Scene {
+    let (width, height) = SYSTEM_SIZE;
+    let mut scene = load_scene(width, height, sphere, asteroids);
+    for _ in 0..TIME {
+        scene.step();
+    }
+    scene
+}
+
+fn solar_system_camera() -> Camera {
+    Camera::new(Vec3::new(0.0, 8.0, 24.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0))
+}
+
+fn solar_system_with(sphere: SphereKind, asteroids: usize) -> RgbImage {
+    render(&solar_system_scene(sphere, asteroids), &solar_system_camera(), SYSTEM_SIZE)
+}
+
+#[test]
+fn solar_system() {
+    check_golden("solar_system", &solar_system_with(SphereKind::Uv, 0));
+}
+
+#[test]
+fn solar_system_icospheres() {
+    check_golden("solar_system_ico", &solar_system_with(SphereKind::Ico, 0));
+}
+
+// What the window shows without options
+#[test]
+fn solar_system_default_presentation() {
+    let scene = solar_system_scene(SphereKind::Uv, 0);
+    let framebuffer = render_framebuffer(&scene, &solar_system_camera(), SYSTEM_SIZE, &Presentation::default());
+    check_golden("solar_system_default", &to_rgb_image(&framebuffer.buffer, framebuffer.width, framebuffer.height));
+}
+
+#[test]
+fn solar_system_with_asteroid_belt() {
+    check_golden("solar_system_belt", &solar_system_with(SphereKind::Uv, ASTEROIDS));
+}
+
+#[test]
+fn debug_views() {
+    let sphere = body("assets/sphere.obj", 4.0, 2.0, Vec3::new(0.3, 0.6, 0.0));
+    let mut scene = single(sphere);
+    for (name, view) in [("depth_view", DebugView::Depth), ("normal_view", DebugView::Normal), ("uv_view", DebugView::TexCoords)] {
+        scene.debug_view = view;
+        check_golden(name, &render(&scene, &close_up_camera(), SHADER_SIZE));
+    }
+}
+
+// Copies drawn in one instanced batch look exactly like separate objects
+#[test]
+fn instances_match_separate_objects() {
+    let copies = [(Vec3::new(-0.8, 0.0, 0.0), 0.5), (Vec3::new(0.7, 0.3, -1.0), 0.8), (Vec3::new(0.3, -0.6, 0.8), 0.4)];
+    let template = body("assets/sphere.obj", 6.0, 1.0, Vec3::new(0.3, 0.6, 0.0));
+
+    let mut separate = single(body("assets/sphere.obj", 6.0, 1.0, Vec3::zeros()));
+    separate.objects = copies
+        .iter()
+        .map(|&(translation, scale)| SceneObject { translation, scale, mesh: template.mesh.clone(), ..body("assets/sphere.obj", 6.0, 1.0, template.rotation) })
+        .collect();
+    separate.orbit_angles = vec![0.0; copies.len()];
+    separate.orbit_speeds = vec![0.0; copies.len()];
+
+    let mut instanced = single(body("assets/sphere.obj", 6.0, 1.0, Vec3::zeros()));
+    instanced.objects.clear();
+    instanced.orbit_angles.clear();
+    instanced.orbit_speeds.clear();
+    let instances = copies
+        .iter()
+        .map(|&(translation, scale)| Instance { translation, rotation: template.rotation, scale, orbit_speed: 0.0, spin_speed: 0.0 })
+        .collect();
+    instanced.instanced.push(InstancedObject { object: template, instances });
+
+    assert!(render(&separate, &close_up_camera(), SHADER_SIZE) == render(&instanced, &close_up_camera(), SHADER_SIZE));
+}
+
+// The tiles are shaded in parallel, but each pixel only ever sees its own
+// fragments in submission order, so the thread count cannot change the image
+#[test]
+fn thread_count_does_not_change_the_frame() {
+    let overlapping = [
+        (Vec3::new(-0.4, 0.0, 0.0), 4.0, BlendMode::Replace, 1.0),
+        (Vec3::new(0.4, 0.2, -0.5), 6.0, BlendMode::Replace, 1.0),
+        (Vec3::new(0.0, -0.2, 0.6), 5.0, BlendMode::Normal, 0.5),
+        (Vec3::new(0.3, 0.3, 0.9), 1.0, BlendMode::Add, 0.4),
+    ];
+    let mut scene = single(body("assets/sphere.obj", 4.0, 1.0, Vec3::zeros()));
+    scene.objects = overlapping
+        .iter()
+        .map(|&(translation, id, blend_mode, opacity)| SceneObject {
+            translation,
+            blend_mode,
+            opacity,
+            ..body("assets/sphere.obj", id, 0.8, Vec3::new(0.3, 0.6, 0.0))
+        })
+        .collect();
+    scene.orbit_angles = vec![0.0; overlapping.len()];
+    scene.orbit_speeds = vec![0.0; overlapping.len()];
+
+    let render_with = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| render_framebuffer(&scene, &close_up_camera(), SYSTEM_SIZE, &aces_with_bloom()))
+    };
+    let serial = render_with(1);
+    let parallel = render_with(8);
+
+    let bits = |colors: &[HdrColor]| colors.iter().flat_map(|c| [c.r, c.g, c.b, c.a].map(f32::to_bits)).collect::<Vec<_>>();
+    assert!(serial.buffer == parallel.buffer);
+    assert!(bits(&serial.color_samples) == bits(&parallel.color_samples));
+    assert!(bits(&serial.emission_samples) == bits(&parallel.emission_samples));
+    assert!(serial.zbuffer.iter().map(|z| z.to_bits()).eq(parallel.zbuffer.iter().map(|z| z.to_bits())));
+}
+
+// Clipping adds vertices where the triangle meets the frustum, and the fan
+// around the first one shares it; neither makes another point
+#[test]
+fn points_mark_each_mesh_vertex_once() {
+    // The third corner is behind the camera
+    let corners = [Vec3::new(-0.5, -0.5, 0.0), Vec3::new(0.5, -0.5, 0.0), Vec3::new(0.0, 0.5, 10.0)];
+    let vertices = corners.map(|position| Vertex::new(position, Vec3::new(0.0, 0.0, 1.0), Vec2::zeros())).to_vec();
+    let mesh = Mesh::new(vertices, vec![0, 1, 2]);
+    let mut scene = single(SceneObject { bounds: mesh.bounding_sphere(), mesh: Lod::single(mesh), ..body("assets/sphere.obj", 1.0, 1.0, Vec3::zeros()) });
+    scene.render_mode = RenderMode::Points;
+
+    let (width, height) = SHADER_SIZE;
+    let mut framebuffer = Framebuffer::new_multisampled(width, height, MSAA_SAMPLES);
+    framebuffer.clear();
+    let stats = render_scene(&mut framebuffer, &scene, &close_up_camera(), TIME, 1);
+    finish_frame(&mut framebuffer, &Presentation::default(), &PostChain::default(), TIME);
+
+    // Two squares, one per corner in front of the camera, each drawn once
+    let side = (2 * (POINT_SIZE / 2) + 1) as usize;
+    assert_eq!(framebuffer.buffer.iter().filter(|&&pixel| pixel != 0).count(), 2 * side * side);
+    assert_eq!(stats.early_depth_rejected, 0);
+}
+
+// A sphere shaded with fragment shader `id`, filling most of the frame
+fn check_shader(name: &str, id: f32) {
+    let sphere = body("assets/sphere.obj", id, 2.0, Vec3::new(0.3, 0.6, 0.0));
+    let image = render(&single(sphere), &close_up_camera(), SHADER_SIZE);
+    check_golden(name, &image);
+}
+
+fn close_up_camera() -> Camera {
+    Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0))
+}
+
+fn body(model: &str, id: f32, scale: f32, rotation: Vec3) -> SceneObject {
+    let obj = Obj::load(model).expect("Failed to load obj");
+    SceneObject {
+        translation: Vec3::zeros(),
+        rotation,
+        scale,
+        mesh: Lod::single(obj.get_mesh()),
+        bounds: obj.bounding_sphere(),
+        id,
+        cull_mode: CullMode::None,
+        front_face: FrontFace::CounterClockwise,
+        early_depth_test: true,
+        blend_mode: BlendMode::Replace,
+        opacity: 1.0,
+    }
+}
+
+// A scene with a single still object at the origin and no stars or nave
+fn single(object: SceneObject) -> Scene {
+    let nave = SceneObject { mesh: Lod::single(Mesh::default()), ..body("assets/sphere.obj", 0.0, 1.0, Vec3::zeros()) };
+    Scene {
+        objects: vec![object],
+        instanced: Vec::new(),
+        nave,
+        show_nave: false,
+        stars: Vec::new(),
+        render_mode: RenderMode::Solid,
+        show_guides: false,
+        debug_view: DebugView::Shaded,
+        orbit_angles: vec![0.0],
+        orbit_speeds: vec![0.0],
+    }
+}
+
+// The full frame pipeline of the window, with bloom and without post effects
+fn render(scene: &Scene, camera: &Camera, size: (usize, usize)) -> RgbImage {
+    let framebuffer = render_framebuffer(scene, camera, size, &aces_with_bloom());
+    to_rgb_image(&framebuffer.buffer, framebuffer.width, framebuffer.height)
+}
+
+// Most goldens go through every stage of the presentation
+fn aces_with_bloom() -> Presentation {
+    Presentation { tone_mapping: ToneMapping::Aces, exposure: 1.0, bloom: Some(Bloom::default()) }
+}
+
+fn render_framebuffer(scene: &Scene, camera: &Camera, (width, height): (usize, usize), presentation: &Presentation) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new_multisampled(width, height, MSAA_SAMPLES);
+    framebuffer.clear();
+    render_scene(&mut framebuffer, scene, camera, TIME, 1);
+    match scene.debug_view {
+        DebugView::Shaded => finish_frame(&mut framebuffer, presentation, &PostChain::default(), TIME),
+        view => finish_debug_frame(&mut framebuffer, view),
+    }
+    framebuffer
+}
+
+fn check_golden(name: &str, image: &RgbImage) {
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        image.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.into_rgb8(),
+        Err(err) => panic!(
+            "cannot read {}: {}; run `UPDATE_GOLDEN=1 cargo test --test golden` to create it",
+            reference_path.display(),
+            err
+        ),
+    };
+    assert_eq!(reference.dimensions(), image.dimensions(), "{} has changed size", name);
+
+    let comparison = compare(&reference, image);
+    let pixels = (image.width() * image.height()) as f64;
+    if comparison.mismatched as f64 / pixels > MAX_MISMATCHED || comparison.psnr < MIN_PSNR {
+        let output = failure_dir();
+        let actual_path = output.join(format!("{}.actual.png", name));
+        let diff_path = output.join(format!("{}.diff.png", name));
+        image.save(&actual_path).unwrap();
+        comparison.diff.save(&diff_path).unwrap();
+        panic!(
+            "{} differs from its reference: {} pixels off by more than {}, PSNR {:.1} dB (minimum {} dB); see {} and {}",
+            name,
+            comparison.mismatched,
+            TOLERANCE,
+            comparison.psnr,
+            MIN_PSNR,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn failure_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+struct Comparison {
+    mismatched: usize,
+    psnr: f64,
+    diff: RgbImage,
+}
+
+fn compare(reference: &RgbImage, image: &RgbImage) -> Comparison {
+    let mut diff = RgbImage::new(image.width(), image.height());
+    let mut mismatched = 0;
+    let mut squared_error = 0.0;
+
+    for ((expected, actual), out) in reference.pixels().zip(image.pixels()).zip(diff.pixels_mut()) {
+        let mut largest = 0;
+        for (&e, &a) in expected.0.iter().zip(&actual.0) {
+            let difference = e.abs_diff(a);
+            largest = largest.max(difference);
+            squared_error += (difference as f64).powi(2);
+        }
+
+        *out = if largest > TOLERANCE {
+            mismatched += 1;
+            Rgb([255, 0, 0])
+        } else {
+            Rgb(expected.0.map(|c| c / 4))
+        };
+    }
+
+    let mse = squared_error / (image.width() * image.height() * 3) as f64;
+    let psnr = if mse == 0.0 { f64::INFINITY } else { 10.0 * (255.0 * 255.0 / mse).log10() };
+    Comparison { mismatched, psnr, diff }
+}